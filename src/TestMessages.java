@@ -0,0 +1,250 @@
+// Generated by bit_helper from a schema file. Do not edit this file manually.
+
+import java.util.ArrayList;
+import java.util.List;
+
+import nl.knokko.util.bits.BitInput;
+import nl.knokko.util.bits.BitOutput;
+
+public final class TestMessages {
+
+    private TestMessages() {}
+
+    public static class Item {
+
+        public int id;
+        public String name;
+
+        public void write(BitOutput output) {
+            output.addInt(this.id);
+            output.addString(this.name);
+        }
+
+        public static Item read(BitInput input) {
+            Item result = new Item();
+            int field_id = input.readInt();
+            result.id = field_id;
+            String field_name = input.readString();
+            if (field_name != null && field_name.length() > 50) {
+                throw new IllegalArgumentException("The string is longer than 50");
+            }
+            result.name = field_name;
+            return result;
+        }
+    }
+
+    public static class Player {
+
+        public boolean alive;
+        public byte small;
+        public short medium;
+        public char letter;
+        public long big;
+        public String guild;
+        public Item weapon;
+        public List<Long> scores;
+        public List<Item> items;
+        public List<List<Byte>> nested;
+        public List<Player> children;
+
+        public void write(BitOutput output) {
+            output.addBoolean(this.alive);
+            output.addByte(this.small);
+            output.addShort(this.medium);
+            output.addChar(this.letter);
+            output.addLong(this.big);
+            output.addString(this.guild);
+            output.addBoolean(this.weapon != null);
+            if (this.weapon != null) {
+                this.weapon.write(output);
+            }
+            output.addInt(this.scores.size());
+            for (Long element_0 : this.scores) {
+                output.addBoolean(element_0 != null);
+                if (element_0 != null) {
+                    output.addLong(element_0);
+                }
+            }
+            output.addInt(this.items.size());
+            for (Item element_0 : this.items) {
+                element_0.write(output);
+            }
+            output.addInt(this.nested.size());
+            for (List<Byte> element_0 : this.nested) {
+                output.addInt(element_0.size());
+                for (Byte element_1 : element_0) {
+                    output.addByte(element_1);
+                }
+            }
+            output.addInt(this.children.size());
+            for (Player element_0 : this.children) {
+                element_0.write(output);
+            }
+        }
+
+        public static Player read(BitInput input) {
+            Player result = new Player();
+            boolean field_alive = input.readBoolean();
+            result.alive = field_alive;
+            byte field_small = input.readByte();
+            result.small = field_small;
+            short field_medium = input.readShort();
+            result.medium = field_medium;
+            char field_letter = input.readChar();
+            result.letter = field_letter;
+            long field_big = input.readLong();
+            result.big = field_big;
+            String field_guild = input.readString();
+            if (field_guild != null && field_guild.length() > 20) {
+                throw new IllegalArgumentException("The string is longer than 20");
+            }
+            result.guild = field_guild;
+            Item field_weapon = null;
+            if (input.readBoolean()) {
+                Item value_0 = Item.read(input);
+                field_weapon = value_0;
+            }
+            result.weapon = field_weapon;
+            int length_1 = input.readInt();
+            if (length_1 < 0 || length_1 > 10) {
+                throw new IllegalArgumentException("Invalid list length " + length_1);
+            }
+            List<Long> field_scores = new ArrayList<>(length_1);
+            for (int index_1 = 0; index_1 < length_1; index_1++) {
+                Long element_1 = null;
+                if (input.readBoolean()) {
+                    long value_2 = input.readLong();
+                    element_1 = value_2;
+                }
+                field_scores.add(element_1);
+            }
+            result.scores = field_scores;
+            int length_3 = input.readInt();
+            if (length_3 < 0 || length_3 > 100) {
+                throw new IllegalArgumentException("Invalid list length " + length_3);
+            }
+            List<Item> field_items = new ArrayList<>(length_3);
+            for (int index_3 = 0; index_3 < length_3; index_3++) {
+                Item element_3 = Item.read(input);
+                field_items.add(element_3);
+            }
+            result.items = field_items;
+            int length_4 = input.readInt();
+            if (length_4 < 0 || length_4 > 3) {
+                throw new IllegalArgumentException("Invalid list length " + length_4);
+            }
+            List<List<Byte>> field_nested = new ArrayList<>(length_4);
+            for (int index_4 = 0; index_4 < length_4; index_4++) {
+                int length_5 = input.readInt();
+                if (length_5 < 0 || length_5 > 3) {
+                    throw new IllegalArgumentException("Invalid list length " + length_5);
+                }
+                List<Byte> element_4 = new ArrayList<>(length_5);
+                for (int index_5 = 0; index_5 < length_5; index_5++) {
+                    byte element_5 = input.readByte();
+                    element_4.add(element_5);
+                }
+                field_nested.add(element_4);
+            }
+            result.nested = field_nested;
+            int length_6 = input.readInt();
+            if (length_6 < 0 || length_6 > 4) {
+                throw new IllegalArgumentException("Invalid list length " + length_6);
+            }
+            List<Player> field_children = new ArrayList<>(length_6);
+            for (int index_6 = 0; index_6 < length_6; index_6++) {
+                Player element_6 = Player.read(input);
+                field_children.add(element_6);
+            }
+            result.children = field_children;
+            return result;
+        }
+    }
+
+    public static class Names {
+
+        public List<Integer> items;
+        public int items_length;
+        public int items_index;
+        public List<Byte> items_element;
+        public Short weapon;
+        public short weapon_value;
+        public int length_0;
+        public Integer value_0;
+
+        public void write(BitOutput output) {
+            output.addInt(this.items.size());
+            for (Integer element_0 : this.items) {
+                output.addInt(element_0);
+            }
+            output.addInt(this.items_length);
+            output.addInt(this.items_index);
+            output.addInt(this.items_element.size());
+            for (Byte element_0 : this.items_element) {
+                output.addBoolean(element_0 != null);
+                if (element_0 != null) {
+                    output.addByte(element_0);
+                }
+            }
+            output.addBoolean(this.weapon != null);
+            if (this.weapon != null) {
+                output.addShort(this.weapon);
+            }
+            output.addShort(this.weapon_value);
+            output.addInt(this.length_0);
+            output.addBoolean(this.value_0 != null);
+            if (this.value_0 != null) {
+                output.addInt(this.value_0);
+            }
+        }
+
+        public static Names read(BitInput input) {
+            Names result = new Names();
+            int length_0 = input.readInt();
+            if (length_0 < 0 || length_0 > 5) {
+                throw new IllegalArgumentException("Invalid list length " + length_0);
+            }
+            List<Integer> field_items = new ArrayList<>(length_0);
+            for (int index_0 = 0; index_0 < length_0; index_0++) {
+                int element_0 = input.readInt();
+                field_items.add(element_0);
+            }
+            result.items = field_items;
+            int field_items_length = input.readInt();
+            result.items_length = field_items_length;
+            int field_items_index = input.readInt();
+            result.items_index = field_items_index;
+            int length_1 = input.readInt();
+            if (length_1 < 0 || length_1 > 5) {
+                throw new IllegalArgumentException("Invalid list length " + length_1);
+            }
+            List<Byte> field_items_element = new ArrayList<>(length_1);
+            for (int index_1 = 0; index_1 < length_1; index_1++) {
+                Byte element_1 = null;
+                if (input.readBoolean()) {
+                    byte value_2 = input.readByte();
+                    element_1 = value_2;
+                }
+                field_items_element.add(element_1);
+            }
+            result.items_element = field_items_element;
+            Short field_weapon = null;
+            if (input.readBoolean()) {
+                short value_3 = input.readShort();
+                field_weapon = value_3;
+            }
+            result.weapon = field_weapon;
+            short field_weapon_value = input.readShort();
+            result.weapon_value = field_weapon_value;
+            int field_length_0 = input.readInt();
+            result.length_0 = field_length_0;
+            Integer field_value_0 = null;
+            if (input.readBoolean()) {
+                int value_4 = input.readInt();
+                field_value_0 = value_4;
+            }
+            result.value_0 = field_value_0;
+            return result;
+        }
+    }
+}
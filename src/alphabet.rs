@@ -0,0 +1,188 @@
+use crate::input::*;
+use crate::output::*;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+/**
+ * An Alphabet is a set of characters that can be used to store strings compactly. Strings that only
+ * consist of characters of the alphabet can be stored with the add_alphabet_string method of BitOutput
+ * and loaded again with the read_alphabet_string method of BitInput.
+ *
+ * Every character is stored as its index in the alphabet using a truncated binary code: when the alphabet
+ * has n characters, every character takes either floor(log2(n)) or ceil(log2(n)) bits. So a character of a
+ * hexadecimal string takes 4 bits and a character of a lowercase name takes 4 or 5 bits.
+ *
+ * The order of the characters matters: the alphabet used to read a string must have exactly the same
+ * characters in the same order as the alphabet that was used to store it.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alphabet {
+    chars: Vec<char>,
+    indices: HashMap<char, u32>,
+    short_bits: usize,
+    short_codes: u32,
+}
+
+impl Alphabet {
+    /**
+     * Creates a new alphabet that consists of the characters of the given string, in the order in which
+     * they appear in the string. Characters that occur more than once will only be added once.
+     */
+    pub fn new(chars: &str) -> Alphabet {
+        let mut unique_chars = Vec::new();
+        let mut indices = HashMap::new();
+        for character in chars.chars() {
+            if let Entry::Vacant(entry) = indices.entry(character) {
+                entry.insert(unique_chars.len() as u32);
+                unique_chars.push(character);
+            }
+        }
+
+        // The truncated binary code uses short_bits bits for the first short_codes characters
+        // and short_bits + 1 bits for the remaining characters
+        let size = unique_chars.len() as u64;
+        let mut short_bits = 0;
+        while size >> (short_bits + 1) > 0 {
+            short_bits += 1;
+        }
+        let short_codes = ((2 << short_bits) - size) as u32;
+
+        Alphabet {
+            chars: unique_chars,
+            indices,
+            short_bits,
+            short_codes,
+        }
+    }
+
+    /**
+     * The alphabet of lowercase hexadecimal digits: 0123456789abcdef
+     */
+    pub fn hex() -> Alphabet {
+        Alphabet::new("0123456789abcdef")
+    }
+
+    /**
+     * The base32 alphabet of RFC 4648: ABCDEFGHIJKLMNOPQRSTUVWXYZ234567
+     */
+    pub fn base32() -> Alphabet {
+        Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ234567")
+    }
+
+    /**
+     * The alphabet of the lowercase latin letters: abcdefghijklmnopqrstuvwxyz
+     */
+    pub fn lowercase() -> Alphabet {
+        Alphabet::new("abcdefghijklmnopqrstuvwxyz")
+    }
+
+    /**
+     * Returns the number of characters in this alphabet.
+     */
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    /**
+     * Returns true if this alphabet doesn't have any characters. Only empty strings can be stored with an
+     * empty alphabet.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /**
+     * Returns the characters of this alphabet, in order.
+     */
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /**
+     * Returns the index of the given character in this alphabet, or None if the character is not part of
+     * this alphabet.
+     */
+    pub fn index_of(&self, character: char) -> Option<u32> {
+        self.indices.get(&character).copied()
+    }
+
+    /**
+     * Returns true if the given character is part of this alphabet.
+     */
+    pub fn contains(&self, character: char) -> bool {
+        self.indices.contains_key(&character)
+    }
+
+    /**
+     * Returns the maximum number of bits a single character of this alphabet will take.
+     */
+    pub fn max_bits_per_char(&self) -> usize {
+        if self.short_codes as usize >= self.chars.len() {
+            self.short_bits
+        } else {
+            self.short_bits + 1
+        }
+    }
+
+    /**
+     * Adds the code of the character at the given index to the output without checking its capacity.
+     */
+    pub(crate) fn add_direct_index<O: BitOutput + ?Sized>(&self, output: &mut O, index: u32) {
+        if index < self.short_codes {
+            output.add_direct_sized_u64(index as u64, self.short_bits);
+        } else {
+            output.add_direct_sized_u64((index + self.short_codes) as u64, self.short_bits + 1);
+        }
+    }
+
+    /**
+     * Reads the code of a character from the input and returns the corresponding character.
+     */
+    pub(crate) fn read_char<I: BitInput + ?Sized>(
+        &self,
+        input: &mut I,
+    ) -> Result<char, BitInputError> {
+        if self.chars.is_empty() {
+            return Err(BitInputError::InvalidString(InvalidStringError));
+        }
+        let mut code = input.read_sized_u64(self.short_bits)? as u32;
+        if code >= self.short_codes {
+            code = 2 * code + input.read_bool()? as u32 - self.short_codes;
+        }
+        Ok(self.chars[code as usize])
+    }
+}
+
+/**
+ * This error is returned by the add_alphabet_string method of BitOutput when the string contains a character
+ * that is not part of the alphabet. Nothing will be written to the bit output when this error is returned.
+ */
+#[derive(Debug, PartialEq)]
+pub struct CharNotInAlphabetError {
+    character: char,
+}
+
+impl CharNotInAlphabetError {
+    pub(crate) fn new(character: char) -> CharNotInAlphabetError {
+        CharNotInAlphabetError { character }
+    }
+
+    /**
+     * The character that is not part of the alphabet.
+     */
+    pub fn character(&self) -> char {
+        self.character
+    }
+}
+
+impl std::fmt::Display for CharNotInAlphabetError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "The character {:?} is not part of the alphabet",
+            self.character
+        )
+    }
+}
+
+impl std::error::Error for CharNotInAlphabetError {}
@@ -0,0 +1,421 @@
+use crate::converter::*;
+use crate::input::*;
+use crate::output::*;
+use crate::packed::*;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Range};
+
+/**
+ * A growable vector of bits that are packed in u64 words, so every bit really takes only 1 bit of memory
+ * (unlike the bools of a Vec<bool>, which take 1 byte each). Bits can be pushed, popped, read and changed like
+ * the elements of a Vec and BitVecs of the same length can be combined with the bitwise operators &, | and ^.
+ *
+ * BitVec implements BitOutput, so data can be written to it directly. Unlike the byte outputs, a BitVec always
+ * knows exactly how many bits were written: that is simply its len(). The written data can be read again with
+ * a BitVecBitInput.
+ */
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BitVec {
+    // The bits of the last word that are not part of this vector are always 0
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitVec {
+    /**
+     * Creates a new empty BitVec.
+     */
+    pub fn new() -> BitVec {
+        BitVec {
+            words: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /**
+     * Creates a new empty BitVec that can hold at least the given number of bits without reallocating.
+     */
+    pub fn with_capacity(bits: usize) -> BitVec {
+        BitVec {
+            words: Vec::with_capacity(word_count(bits)),
+            len: 0,
+        }
+    }
+
+    /**
+     * Creates a new BitVec with the given length where every bit has the given value.
+     */
+    pub fn filled(len: usize, value: bool) -> BitVec {
+        let mut result = BitVec {
+            words: vec![if value { u64::MAX } else { 0 }; word_count(len)],
+            len,
+        };
+        result.clear_unused_bits();
+        result
+    }
+
+    /**
+     * Creates a new BitVec that contains the given bools.
+     */
+    pub fn from_bools(bools: &[bool]) -> BitVec {
+        bools.iter().copied().collect()
+    }
+
+    /**
+     * Returns the number of bits in this vector.
+     */
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /**
+     * Returns true if this vector doesn't have any bits.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /**
+     * Returns the number of bits this vector can hold without reallocating.
+     */
+    pub fn capacity(&self) -> usize {
+        64 * self.words.capacity()
+    }
+
+    /**
+     * Reserves capacity for at least additional more bits.
+     */
+    pub fn reserve(&mut self, additional: usize) {
+        let required_words = word_count(self.len + additional);
+        self.words
+            .reserve(required_words.saturating_sub(self.words.len()));
+    }
+
+    /**
+     * Adds the given bit to the end of this vector.
+     */
+    pub fn push(&mut self, value: bool) {
+        if self.len == 64 * self.words.len() {
+            self.words.push(0);
+        }
+        self.len += 1;
+        if value {
+            self.words[(self.len - 1) / 64] |= bit_mask(self.len - 1);
+        }
+    }
+
+    /**
+     * Removes the last bit of this vector and returns it, or returns None if this vector is empty.
+     */
+    pub fn pop(&mut self) -> Option<bool> {
+        if self.len == 0 {
+            return None;
+        }
+        let value = self.get(self.len - 1);
+        self.set(self.len - 1, false);
+        self.len -= 1;
+        self.words.truncate(word_count(self.len));
+        Some(value)
+    }
+
+    /**
+     * Returns the bit at the given index. This function will panic if the index is out of bounds.
+     */
+    pub fn get(&self, index: usize) -> bool {
+        self.check_index(index);
+        self.words[index / 64] & bit_mask(index) != 0
+    }
+
+    /**
+     * Changes the bit at the given index. This function will panic if the index is out of bounds.
+     */
+    pub fn set(&mut self, index: usize, value: bool) {
+        self.check_index(index);
+        if value {
+            self.words[index / 64] |= bit_mask(index);
+        } else {
+            self.words[index / 64] &= !bit_mask(index);
+        }
+    }
+
+    /**
+     * Removes all bits from this vector.
+     */
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.len = 0;
+    }
+
+    /**
+     * Shortens this vector to the given length. This has no effect if the vector is not longer than len.
+     */
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.len = len;
+            self.words.truncate(word_count(len));
+            self.clear_unused_bits();
+        }
+    }
+
+    /**
+     * Returns the number of bits that are 1.
+     */
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /**
+     * Returns an iterator over the bits of this vector.
+     */
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(move |index| self.get(index))
+    }
+
+    /**
+     * Copies the bits of this vector to a new Vec<bool>.
+     */
+    pub fn to_bools(&self) -> Vec<bool> {
+        self.iter().collect()
+    }
+
+    /**
+     * Returns a new BitVec that contains a copy of the bits in the given range of this vector. This function
+     * will panic if the range is out of bounds.
+     */
+    pub fn slice(&self, range: Range<usize>) -> BitVec {
+        if range.start > range.end || range.end > self.len {
+            panic!(
+                "The range {:?} is out of bounds for a BitVec of length {}",
+                range, self.len
+            );
+        }
+        let mut result = BitVec::with_capacity(range.len());
+        let shift = range.start % 64;
+        let first_word = range.start / 64;
+        for word_index in 0..word_count(range.len()) {
+            let mut word = self.words[first_word + word_index] << shift;
+            if shift > 0 && first_word + word_index + 1 < self.words.len() {
+                word |= self.words[first_word + word_index + 1] >> (64 - shift);
+            }
+            result.words.push(word);
+        }
+        result.len = range.len();
+        result.clear_unused_bits();
+        result
+    }
+
+    /**
+     * Adds all bits of the other vector to the end of this vector.
+     */
+    pub fn extend_from_bit_vec(&mut self, other: &BitVec) {
+        self.reserve(other.len);
+        for value in other.iter() {
+            self.push(value);
+        }
+    }
+
+    fn combine<F: Fn(u64, u64) -> u64>(&mut self, other: &BitVec, operator: F) {
+        if self.len != other.len {
+            panic!(
+                "Bitwise operators require BitVecs of the same length, but the lengths are {} and {}",
+                self.len, other.len
+            );
+        }
+        for (word, other_word) in self.words.iter_mut().zip(other.words.iter()) {
+            *word = operator(*word, *other_word);
+        }
+    }
+
+    fn clear_unused_bits(&mut self) {
+        let used_bits = self.len % 64;
+        if used_bits > 0 {
+            if let Some(last_word) = self.words.last_mut() {
+                *last_word &= u64::MAX << (64 - used_bits);
+            }
+        }
+    }
+
+    fn check_index(&self, index: usize) {
+        if index >= self.len {
+            panic!(
+                "The index is {}, but the length of the BitVec is {}",
+                index, self.len
+            );
+        }
+    }
+}
+
+/**
+ * Returns the mask of the bit with the given index in its word. The first bit is the most significant bit.
+ */
+fn bit_mask(index: usize) -> u64 {
+    1 << (63 - index % 64)
+}
+
+impl std::iter::FromIterator<bool> for BitVec {
+    fn from_iter<T: IntoIterator<Item = bool>>(iter: T) -> BitVec {
+        let mut result = BitVec::new();
+        result.extend(iter);
+        result
+    }
+}
+
+impl std::iter::Extend<bool> for BitVec {
+    fn extend<T: IntoIterator<Item = bool>>(&mut self, iter: T) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl std::fmt::Debug for BitVec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "BitVec(")?;
+        for value in self.iter() {
+            write!(f, "{}", value as u8)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl BitAndAssign<&BitVec> for BitVec {
+    fn bitand_assign(&mut self, other: &BitVec) {
+        self.combine(other, |a, b| a & b);
+    }
+}
+
+impl BitOrAssign<&BitVec> for BitVec {
+    fn bitor_assign(&mut self, other: &BitVec) {
+        self.combine(other, |a, b| a | b);
+    }
+}
+
+impl BitXorAssign<&BitVec> for BitVec {
+    fn bitxor_assign(&mut self, other: &BitVec) {
+        self.combine(other, |a, b| a ^ b);
+    }
+}
+
+impl BitAnd for &BitVec {
+    type Output = BitVec;
+
+    fn bitand(self, other: &BitVec) -> BitVec {
+        let mut result = self.clone();
+        result &= other;
+        result
+    }
+}
+
+impl BitOr for &BitVec {
+    type Output = BitVec;
+
+    fn bitor(self, other: &BitVec) -> BitVec {
+        let mut result = self.clone();
+        result |= other;
+        result
+    }
+}
+
+impl BitXor for &BitVec {
+    type Output = BitVec;
+
+    fn bitxor(self, other: &BitVec) -> BitVec {
+        let mut result = self.clone();
+        result ^= other;
+        result
+    }
+}
+
+impl Not for &BitVec {
+    type Output = BitVec;
+
+    fn not(self) -> BitVec {
+        let mut result = self.clone();
+        for word in result.words.iter_mut() {
+            *word = !*word;
+        }
+        result.clear_unused_bits();
+        result
+    }
+}
+
+impl BitOutput for BitVec {
+    fn add_direct_bool(&mut self, value: bool) {
+        self.push(value);
+    }
+
+    fn add_direct_i8(&mut self, value: i8) {
+        self.add_direct_bools_from_slice(&i8_to_bool_array(value));
+    }
+
+    fn ensure_extra_capacity(&mut self, extra_bools: usize) {
+        self.reserve(extra_bools);
+    }
+
+    fn terminate(&mut self) {
+        self.words.shrink_to_fit();
+    }
+
+    fn bit_len(&self) -> usize {
+        self.len
+    }
+}
+
+/**
+ * A BitInput that reads the bits of a BitVec, starting with its first bit. It will never read more bits than
+ * the length of the BitVec.
+ */
+pub struct BitVecBitInput<'a> {
+    bits: &'a BitVec,
+    read_index: usize,
+}
+
+impl<'a> BitVecBitInput<'a> {
+    pub fn new(bits: &'a BitVec) -> BitVecBitInput<'a> {
+        BitVecBitInput {
+            bits,
+            read_index: 0,
+        }
+    }
+
+    /**
+     * Returns the number of bits that have been read so far.
+     */
+    pub fn read_index(&self) -> usize {
+        self.read_index
+    }
+}
+
+impl<'a> BitInput for BitVecBitInput<'a> {
+    fn read_direct_bool(&mut self) -> bool {
+        let result = self.bits.get(self.read_index);
+        self.read_index += 1;
+        result
+    }
+
+    fn read_direct_i8(&mut self) -> i8 {
+        let mut bools = [false; 8];
+        for value in bools.iter_mut() {
+            *value = self.read_direct_bool();
+        }
+        bool_array_to_i8(bools)
+    }
+
+    fn ensure_extra_capacity(&mut self, additional: usize) -> Result<(), InputCapacityError> {
+        if self.read_index + additional > self.bits.len() {
+            Err(InputCapacityError::new(
+                self.read_index,
+                self.bits.len(),
+                additional,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn terminate(&mut self) {
+        self.read_index = self.bits.len();
+    }
+}
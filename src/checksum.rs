@@ -0,0 +1,285 @@
+use crate::bit_vec::*;
+use crate::converter::*;
+use crate::input::*;
+use crate::output::*;
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut value = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            value = if value & 1 == 1 {
+                (value >> 1) ^ 0xEDB8_8320
+            } else {
+                value >> 1
+            };
+            bit += 1;
+        }
+        table[index] = value;
+        index += 1;
+    }
+    table
+}
+
+/**
+ * Computes the CRC-32 checksum (the one that is used by zip, png and ethernet) of the given bytes.
+ */
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for byte in bytes {
+        crc = (crc >> 8) ^ CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize];
+    }
+    !crc
+}
+
+/**
+ * Computes the Adler-32 checksum (the one that is used by zlib) of the given bytes. It is faster to compute
+ * than CRC-32, but it detects less errors, especially in short data.
+ */
+pub fn adler32(bytes: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let mut a = 1;
+    let mut b = 0;
+    // 5552 is the largest number of bytes that can be added before b could overflow
+    for chunk in bytes.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MODULUS;
+        b %= MODULUS;
+    }
+    (b << 16) | a
+}
+
+/**
+ * The checksum algorithms that can be used by ChecksummedBitOutput.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Crc32,
+    Adler32,
+}
+
+impl ChecksumAlgorithm {
+    /**
+     * Computes the checksum of the given bytes with this algorithm.
+     */
+    pub fn compute(&self, bytes: &[u8]) -> u32 {
+        match self {
+            ChecksumAlgorithm::Crc32 => crc32(bytes),
+            ChecksumAlgorithm::Adler32 => adler32(bytes),
+        }
+    }
+
+    /**
+     * Computes the checksum of a section with the given bits. The bits are packed in bytes (the first bit is the
+     * least significant bit of the first byte) and the number of bits is appended as 8 little-endian bytes, so
+     * sections with the same bytes but a different number of bits get different checksums.
+     */
+    fn compute_section(&self, bits: &BitVec) -> u32 {
+        let mut bytes = vec![0; bits.len().div_ceil(8)];
+        for (index, value) in bits.iter().enumerate() {
+            if value {
+                bytes[index / 8] |= 1 << (index % 8);
+            }
+        }
+        bytes.extend_from_slice(&(bits.len() as u64).to_le_bytes());
+        self.compute(&bytes)
+    }
+}
+
+/**
+ * A BitOutput that wraps another BitOutput and protects the data that is written to it with a checksum. The
+ * data is buffered until terminate (or finish) is called. At that moment, the data is written to the wrapped
+ * output as a section that consists of:
+ * - 1 bool that tells which checksum algorithm is used
+ * - the number of bits of the data (stored with add_var_u64)
+ * - the data itself
+ * - the 32-bit checksum of the data (stored with add_sized_u64)
+ *
+ * Such a section can be read with a ChecksummedBitInput, which will verify the checksum before any of the data
+ * can be read. Because every section stores its own length, multiple sections can be written to the same
+ * output by using finish instead of terminate.
+ */
+pub struct ChecksummedBitOutput<O: BitOutput> {
+    inner: O,
+    algorithm: ChecksumAlgorithm,
+    data: BitVec,
+}
+
+impl<O: BitOutput> ChecksummedBitOutput<O> {
+    /**
+     * Creates a new ChecksummedBitOutput that will write a section with the given checksum algorithm to the
+     * given output.
+     */
+    pub fn new(inner: O, algorithm: ChecksumAlgorithm) -> ChecksummedBitOutput<O> {
+        ChecksummedBitOutput {
+            inner,
+            algorithm,
+            data: BitVec::new(),
+        }
+    }
+
+    /**
+     * Writes the section to the wrapped output without terminating it and returns the wrapped output. More
+     * data (for instance another section) can be written to it afterwards.
+     */
+    pub fn finish(mut self) -> O {
+        self.write_section();
+        self.inner
+    }
+
+    /**
+     * Returns a reference to the wrapped output. Note that the data of the current section is only written to
+     * it when terminate or finish is called.
+     */
+    pub fn get_ref(&self) -> &O {
+        &self.inner
+    }
+
+    /**
+     * Returns the wrapped output. This should only be used after terminate has been called, because the data of
+     * the current section will be lost otherwise.
+     */
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+
+    fn write_section(&mut self) {
+        let checksum = self.algorithm.compute_section(&self.data);
+        self.inner
+            .add_bool(self.algorithm == ChecksumAlgorithm::Adler32);
+        self.inner.add_var_u64(self.data.len() as u64);
+        let mut data_input = BitVecBitInput::new(&self.data);
+        self.inner
+            .add_bits_from_input(&mut data_input, self.data.len())
+            .expect("The data input should have exactly enough bits");
+        self.inner.add_sized_u64(checksum as u64, 32);
+        self.data.clear();
+    }
+}
+
+impl<O: BitOutput> BitOutput for ChecksummedBitOutput<O> {
+    fn add_direct_bool(&mut self, value: bool) {
+        self.data.push(value);
+    }
+
+    fn add_direct_i8(&mut self, value: i8) {
+        self.data.add_direct_i8(value);
+    }
+
+    fn ensure_extra_capacity(&mut self, extra_bools: usize) {
+        self.data.reserve(extra_bools);
+    }
+
+    /**
+     * Writes the section with the data and its checksum to the wrapped output and terminates the wrapped
+     * output. This should be called exactly once.
+     */
+    fn terminate(&mut self) {
+        self.write_section();
+        self.inner.terminate();
+    }
+
+    fn bit_len(&self) -> usize {
+        self.data.len()
+    }
+}
+
+/**
+ * A BitInput that reads a section that was written by a ChecksummedBitOutput. The whole section is read and its
+ * checksum is verified when this input is created, so none of the data can be read if the section is
+ * corrupted. This input can only read the data of the section.
+ */
+pub struct ChecksummedBitInput {
+    algorithm: ChecksumAlgorithm,
+    data: BitVec,
+    read_index: usize,
+}
+
+impl ChecksummedBitInput {
+    /**
+     * Reads the next section from the given input and verifies its checksum. If the stored checksum doesn't
+     * match the data, a ChecksumMismatchError will be returned. If the section has more than max_bits bits of
+     * data, a LengthError will be returned instead of allocating memory for the data.
+     */
+    pub fn new(
+        input: &mut dyn BitInput,
+        max_bits: usize,
+    ) -> Result<ChecksummedBitInput, BitInputError> {
+        let algorithm = if input.read_bool()? {
+            ChecksumAlgorithm::Adler32
+        } else {
+            ChecksumAlgorithm::Crc32
+        };
+        let bit_count = read_length(input, max_bits)?;
+        input.ensure_extra_capacity(bit_count.saturating_add(32))?;
+        let mut data = BitVec::with_capacity(bit_count);
+        data.add_bits_from_input(input, bit_count)?;
+        let stored_checksum = input.read_sized_u64(32)? as u32;
+        let computed_checksum = algorithm.compute_section(&data);
+        if stored_checksum != computed_checksum {
+            return Err(BitInputError::ChecksumMismatch(ChecksumMismatchError::new(
+                stored_checksum,
+                computed_checksum,
+            )));
+        }
+        Ok(ChecksummedBitInput {
+            algorithm,
+            data,
+            read_index: 0,
+        })
+    }
+
+    /**
+     * Returns the checksum algorithm that was used for the section.
+     */
+    pub fn algorithm(&self) -> ChecksumAlgorithm {
+        self.algorithm
+    }
+
+    /**
+     * Returns the number of bits of data in the section.
+     */
+    pub fn bit_len(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl BitInput for ChecksummedBitInput {
+    fn read_direct_bool(&mut self) -> bool {
+        let result = self.data.get(self.read_index);
+        self.read_index += 1;
+        result
+    }
+
+    fn read_direct_i8(&mut self) -> i8 {
+        let mut bools = [false; 8];
+        for value in bools.iter_mut() {
+            *value = self.read_direct_bool();
+        }
+        bool_array_to_i8(bools)
+    }
+
+    fn ensure_extra_capacity(&mut self, extra_bools: usize) -> Result<(), InputCapacityError> {
+        if self.read_index + extra_bools > self.data.len() {
+            Err(InputCapacityError::new(
+                self.read_index,
+                self.data.len(),
+                extra_bools,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn terminate(&mut self) {
+        self.data.clear();
+        self.read_index = 0;
+    }
+}
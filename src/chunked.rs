@@ -0,0 +1,313 @@
+use crate::input::*;
+use crate::output::*;
+
+/**
+ * Writes records in an indexed container format that allows random access. The records are grouped into
+ * chunks of records_per_chunk records and the bit offset of every chunk is remembered. When the writer is
+ * finished, a table with these offsets is added after the records, so a ChunkedReader can jump straight to
+ * the chunk that contains the record it needs and doesn't need to decode the records before it.
+ *
+ * The format is:
+ * - the records, one after another (chunks are not byte-aligned)
+ * - padding until the next byte boundary
+ * - the table: records_per_chunk, the number of records and the number of bits of the records (all stored
+ *   with add_var_u64), followed by the chunk offsets (stored with add_sorted_u64_seq)
+ * - padding until the next byte boundary
+ * - the byte offset of the table, stored with add_u64 in the last 8 bytes
+ */
+pub struct ChunkedWriter {
+    output: U8VecBitOutput,
+    records_per_chunk: usize,
+    record_count: usize,
+    chunk_offsets: Vec<u64>,
+}
+
+impl ChunkedWriter {
+    /**
+     * Creates a new ChunkedWriter that puts records_per_chunk records in every chunk. Small chunks allow faster
+     * random access and large chunks make the table smaller. This function will panic if records_per_chunk is 0.
+     */
+    pub fn new(records_per_chunk: usize) -> ChunkedWriter {
+        if records_per_chunk == 0 {
+            panic!("The number of records per chunk must be at least 1");
+        }
+        ChunkedWriter {
+            output: U8VecBitOutput::with_capacity(0),
+            records_per_chunk,
+            record_count: 0,
+            chunk_offsets: Vec::new(),
+        }
+    }
+
+    /**
+     * Adds a record. The given write function should write the record to the given bit output. A reader must be
+     * able to decode the record without knowing the records before it, so records should not refer to each
+     * other.
+     */
+    pub fn add_record<F: FnOnce(&mut U8VecBitOutput)>(&mut self, write: F) {
+        if self.record_count == self.records_per_chunk * self.chunk_offsets.len() {
+            self.chunk_offsets.push(self.output.bit_len() as u64);
+        }
+        write(&mut self.output);
+        self.record_count += 1;
+    }
+
+    /**
+     * Returns the number of records that have been added so far.
+     */
+    pub fn record_count(&self) -> usize {
+        self.record_count
+    }
+
+    /**
+     * Adds the chunk table and returns the bytes of the container.
+     */
+    pub fn finish(self) -> Vec<u8> {
+        finish_chunked(
+            self.output,
+            self.records_per_chunk,
+            self.record_count,
+            &self.chunk_offsets,
+        )
+    }
+}
+
+/**
+ * Adds the chunk table after the records in the given output and returns the bytes of the container. This is
+ * used by ChunkedWriter and encode_parallel_chunked.
+ */
+pub(crate) fn finish_chunked(
+    mut output: U8VecBitOutput,
+    records_per_chunk: usize,
+    record_count: usize,
+    chunk_offsets: &[u64],
+) -> Vec<u8> {
+    let data_bit_len = output.bit_len();
+    add_byte_padding(&mut output);
+    let table_offset = output.vector.len();
+    output.add_var_u64(records_per_chunk as u64);
+    output.add_var_u64(record_count as u64);
+    output.add_var_u64(data_bit_len as u64);
+    output.add_sorted_u64_seq(chunk_offsets, GapCodec::Rice);
+    add_byte_padding(&mut output);
+    output.add_u64(table_offset as u64);
+    output.terminate();
+    output.vector
+}
+
+fn add_byte_padding(output: &mut U8VecBitOutput) {
+    while output.bit_len() & 7 != 0 {
+        output.add_bool(false);
+    }
+}
+
+/**
+ * Reads the records of a container that was created by a ChunkedWriter (or by encode_parallel_chunked). The
+ * chunk table is read when the reader is created and the records are only decoded when they are requested, so
+ * any record can be read without decoding all the records before it. The chunks can also be decoded in
+ * parallel.
+ *
+ * The decode functions that are given to the methods of this reader must read exactly 1 record, in the same way
+ * as it was written by the write function that was given to ChunkedWriter::add_record.
+ */
+pub struct ChunkedReader {
+    bytes: Vec<u8>,
+    records_per_chunk: usize,
+    record_count: usize,
+    data_bit_len: usize,
+    chunk_offsets: Vec<u64>,
+}
+
+impl ChunkedReader {
+    /**
+     * Creates a new ChunkedReader for the given container bytes and reads its chunk table. If the bytes are not
+     * a valid container, an error will be returned.
+     *
+     * Because every record should take at least 1 bit, containers with more records than data bits are refused.
+     * Otherwise, a corrupted table could claim an enormous number of records. Containers with records that take
+     * 0 bits (for instance empty structs) can be read with with_empty_records instead.
+     */
+    pub fn new(bytes: Vec<u8>) -> Result<ChunkedReader, BitInputError> {
+        ChunkedReader::read_table(bytes, None)
+    }
+
+    /**
+     * Creates a new ChunkedReader for a container whose records can take 0 bits. The number of records is not
+     * bounded by the number of data bits, so an error will be returned if the container has more than
+     * max_record_count records instead. Apart from that, this function behaves like new.
+     */
+    pub fn with_empty_records(
+        bytes: Vec<u8>,
+        max_record_count: usize,
+    ) -> Result<ChunkedReader, BitInputError> {
+        ChunkedReader::read_table(bytes, Some(max_record_count))
+    }
+
+    fn read_table(
+        bytes: Vec<u8>,
+        max_record_count: Option<usize>,
+    ) -> Result<ChunkedReader, BitInputError> {
+        if bytes.len() < 8 {
+            return Err(BitInputError::InvalidCode(InvalidCodeError));
+        }
+        let table_offset =
+            U8VecRefBitInput::with_start_index(&bytes, bytes.len() - 8).read_u64()?;
+        if table_offset > (bytes.len() - 8) as u64 {
+            return Err(BitInputError::InvalidCode(InvalidCodeError));
+        }
+        let table_offset = table_offset as usize;
+
+        let mut table = U8VecRefBitInput::with_bit_range(&bytes, 8 * table_offset, 8 * bytes.len());
+        let records_per_chunk = table.read_var_u64()?;
+        let record_count = table.read_var_u64()?;
+        let data_bit_len = table.read_var_u64()?;
+        let chunk_offsets = table.read_sorted_u64_seq(8 * bytes.len())?;
+
+        let expected_chunks = match records_per_chunk {
+            0 => None,
+            _ => Some(record_count.div_ceil(records_per_chunk)),
+        };
+        let last_offset = chunk_offsets.last().copied().unwrap_or(0);
+        let max_record_count = match max_record_count {
+            Some(max_record_count) => max_record_count as u64,
+            None => data_bit_len,
+        };
+        if expected_chunks != Some(chunk_offsets.len() as u64)
+            || record_count > max_record_count
+            || data_bit_len > 8 * table_offset as u64
+            || last_offset > data_bit_len
+            || chunk_offsets.first().is_some_and(|offset| *offset != 0)
+        {
+            return Err(BitInputError::InvalidCode(InvalidCodeError));
+        }
+
+        Ok(ChunkedReader {
+            bytes,
+            records_per_chunk: records_per_chunk as usize,
+            record_count: record_count as usize,
+            data_bit_len: data_bit_len as usize,
+            chunk_offsets,
+        })
+    }
+
+    /**
+     * Returns the total number of records in the container.
+     */
+    pub fn record_count(&self) -> usize {
+        self.record_count
+    }
+
+    /**
+     * Returns the number of chunks in the container.
+     */
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_offsets.len()
+    }
+
+    /**
+     * Returns the number of records per chunk. The last chunk can have less records.
+     */
+    pub fn records_per_chunk(&self) -> usize {
+        self.records_per_chunk
+    }
+
+    /**
+     * Returns a bit input that starts at the first record of the chunk with the given index and that can't read
+     * beyond the last record of that chunk. This function will panic if the chunk index is out of bounds.
+     */
+    pub fn chunk_input(&self, chunk_index: usize) -> U8VecRefBitInput<'_> {
+        let start_bit = self.chunk_offsets[chunk_index] as usize;
+        let end_bit = match self.chunk_offsets.get(chunk_index + 1) {
+            Some(next_offset) => *next_offset as usize,
+            None => self.data_bit_len,
+        };
+        U8VecRefBitInput::with_bit_range(&self.bytes, start_bit, end_bit)
+    }
+
+    /**
+     * Decodes all records of the chunk with the given index. This function will panic if the chunk index is out
+     * of bounds.
+     */
+    pub fn decode_chunk<T, F>(&self, chunk_index: usize, decode: F) -> Result<Vec<T>, BitInputError>
+    where
+        F: Fn(&mut U8VecRefBitInput) -> Result<T, BitInputError>,
+    {
+        let first_record = chunk_index * self.records_per_chunk;
+        let chunk_records = (self.record_count - first_record).min(self.records_per_chunk);
+        let mut input = self.chunk_input(chunk_index);
+        (0..chunk_records).map(|_| decode(&mut input)).collect()
+    }
+
+    /**
+     * Decodes the record with the given index. Only the records before it in the same chunk need to be decoded
+     * as well. This function will panic if the record index is out of bounds.
+     */
+    pub fn decode_record<T, F>(&self, record_index: usize, decode: F) -> Result<T, BitInputError>
+    where
+        F: Fn(&mut U8VecRefBitInput) -> Result<T, BitInputError>,
+    {
+        if record_index >= self.record_count {
+            panic!(
+                "The record index is {}, but there are only {} records",
+                record_index, self.record_count
+            );
+        }
+        let mut input = self.chunk_input(record_index / self.records_per_chunk);
+        for _ in 0..record_index % self.records_per_chunk {
+            decode(&mut input)?;
+        }
+        decode(&mut input)
+    }
+
+    /**
+     * Decodes all records of the container, using thread_count threads that decode different chunks at the same
+     * time. The records are returned in order. A thread_count of 0 is treated as 1.
+     */
+    pub fn decode_all_parallel<T, F>(
+        &self,
+        thread_count: usize,
+        decode: F,
+    ) -> Result<Vec<T>, BitInputError>
+    where
+        T: Send,
+        F: Fn(&mut U8VecRefBitInput) -> Result<T, BitInputError> + Sync,
+    {
+        let chunk_count = self.chunk_count();
+        if chunk_count == 0 {
+            return Ok(Vec::new());
+        }
+        let chunks_per_thread = chunk_count.div_ceil(thread_count.max(1));
+        let decode = &decode;
+        let thread_results: Vec<Result<Vec<T>, BitInputError>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..chunk_count)
+                .step_by(chunks_per_thread)
+                .map(|first_chunk| {
+                    scope.spawn(move || {
+                        let bound_chunk = (first_chunk + chunks_per_thread).min(chunk_count);
+                        let mut records = Vec::new();
+                        for chunk_index in first_chunk..bound_chunk {
+                            records.append(&mut self.decode_chunk(chunk_index, decode)?);
+                        }
+                        Ok(records)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    // Let the panic of a decode thread continue in the calling thread
+                    handle
+                        .join()
+                        .unwrap_or_else(|error| std::panic::resume_unwind(error))
+                })
+                .collect()
+        });
+
+        let thread_records = thread_results.into_iter().collect::<Result<Vec<_>, _>>()?;
+        let mut records = Vec::with_capacity(thread_records.iter().map(Vec::len).sum());
+        for mut chunk_records in thread_records {
+            records.append(&mut chunk_records);
+        }
+        Ok(records)
+    }
+}
@@ -0,0 +1,938 @@
+/**
+ * The type of a field in a schema file. All types can be stored by both this library and the java BitHelper
+ * library:
+ * - Bool, I8, I16, Char, I32 and I64 use add_bool, add_i8, add_i16, add_u16, add_i32 and add_i64 (addBoolean,
+ *   addByte, addShort, addChar, addInt and addLong in java)
+ * - String(max_length) uses add_str (addString in java), so it can be None (null in java)
+ * - Message uses the write function that is generated for the message with that name
+ * - Option stores a bool that tells whether the value is present, followed by the value if it is present. The
+ *   value can't be an Option or String, because java would use null for both None and Some(None).
+ * - List(element, max_length) stores the number of elements with add_i32, followed by the elements
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldType {
+    Bool,
+    I8,
+    I16,
+    Char,
+    I32,
+    I64,
+    String(usize),
+    Message(String),
+    Option(Box<FieldType>),
+    List(Box<FieldType>, usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldDefinition {
+    pub name: String,
+    pub field_type: FieldType,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageDefinition {
+    pub name: String,
+    pub fields: Vec<FieldDefinition>,
+}
+
+/**
+ * The parsed contents of a schema file: a list of messages. A schema file looks like this:
+ *
+ * // Comments start with 2 slashes
+ * message Item {
+ *     id: i32;
+ *     name: string(50);
+ * }
+ *
+ * message Player {
+ *     alive: bool;
+ *     letter: char;
+ *     weapon: option<Item>;
+ *     items: list<Item>(100);
+ * }
+ *
+ * The available types are bool, i8, i16, char, i32, i64, string(max_length), option<type>,
+ * list<type>(max_length) and the names of the messages in the file (see FieldType). The max_length of a string
+ * is measured in UTF-16 code units, just like the max_length of read_string.
+ *
+ * The generate_rust and generate_java methods generate the code to read and write the messages in Rust and
+ * java. Both use exactly the same encoding, so messages that are written by one can be read by the other.
+ * Since the generated code is just a String, it can be created from a build script: parse the schema file with
+ * parse_schema, write the result of generate_rust to a file in the OUT_DIR and include! that file.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaFile {
+    pub messages: Vec<MessageDefinition>,
+}
+
+/**
+ * This error is returned by parse_schema when the schema file is invalid. The line is the number of the line
+ * where the problem was found, starting at 1.
+ */
+#[derive(Debug, PartialEq)]
+pub struct SchemaParseError {
+    line: usize,
+    message: String,
+}
+
+impl SchemaParseError {
+    fn new(line: usize, message: String) -> SchemaParseError {
+        SchemaParseError { line, message }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for SchemaParseError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SchemaParseError {
+    fn description(&self) -> &str {
+        "The schema file is invalid"
+    }
+}
+
+// Field and message names can't be keywords of Rust or java, because they are used in both languages
+const RESERVED_NAMES: [&str; 90] = [
+    "_",
+    "abstract",
+    "as",
+    "assert",
+    "async",
+    "await",
+    "become",
+    "boolean",
+    "box",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "crate",
+    "default",
+    "do",
+    "double",
+    "dyn",
+    "else",
+    "enum",
+    "extends",
+    "extern",
+    "false",
+    "final",
+    "finally",
+    "float",
+    "fn",
+    "for",
+    "gen",
+    "goto",
+    "if",
+    "impl",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "int",
+    "interface",
+    "let",
+    "long",
+    "loop",
+    "macro",
+    "match",
+    "mod",
+    "move",
+    "mut",
+    "native",
+    "new",
+    "null",
+    "override",
+    "package",
+    "priv",
+    "private",
+    "protected",
+    "pub",
+    "public",
+    "ref",
+    "return",
+    "Self",
+    "self",
+    "short",
+    "static",
+    "strictfp",
+    "struct",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "trait",
+    "transient",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "union",
+    "unsafe",
+    "unsized",
+    "use",
+    "virtual",
+    "void",
+    "volatile",
+    "where",
+    "while",
+    "yield",
+];
+
+// Message names can't be the names of the types that the generated code uses, because they would hide them
+const RESERVED_MESSAGE_NAMES: [&str; 32] = [
+    "ArrayList",
+    "BitInput",
+    "BitInputError",
+    "BitOutput",
+    "Boolean",
+    "Box",
+    "Byte",
+    "Character",
+    "Err",
+    "IllegalArgumentException",
+    "Integer",
+    "LengthError",
+    "List",
+    "Long",
+    "None",
+    "Object",
+    "Ok",
+    "Option",
+    "Result",
+    "Short",
+    "Some",
+    "String",
+    "Vec",
+    "i8",
+    "i16",
+    "i32",
+    "i64",
+    "u16",
+    "u32",
+    "u64",
+    "usize",
+    "bool",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Identifier(String),
+    Number(usize),
+    Symbol(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, SchemaParseError> {
+    let mut tokens = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = match line.find("//") {
+            Some(comment_index) => &line[..comment_index],
+            None => line,
+        };
+        let mut chars = line.chars().peekable();
+        while let Some(next_char) = chars.next() {
+            if next_char.is_whitespace() {
+                continue;
+            }
+            if next_char.is_ascii_alphabetic() || next_char == '_' {
+                let mut identifier = next_char.to_string();
+                while let Some(next_char) = chars.peek() {
+                    if !next_char.is_ascii_alphanumeric() && *next_char != '_' {
+                        break;
+                    }
+                    identifier.push(*next_char);
+                    chars.next();
+                }
+                tokens.push((Token::Identifier(identifier), line_number));
+            } else if next_char.is_ascii_digit() {
+                let mut digits = next_char.to_string();
+                while let Some(next_char) = chars.peek() {
+                    if !next_char.is_ascii_digit() {
+                        break;
+                    }
+                    digits.push(*next_char);
+                    chars.next();
+                }
+                let number = digits.parse().map_err(|_| {
+                    SchemaParseError::new(
+                        line_number,
+                        format!("The number {} is too large", digits),
+                    )
+                })?;
+                tokens.push((Token::Number(number), line_number));
+            } else if "{}:;<>()".contains(next_char) {
+                tokens.push((Token::Symbol(next_char), line_number));
+            } else {
+                return Err(SchemaParseError::new(
+                    line_number,
+                    format!("Unexpected character '{}'", next_char),
+                ));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        match self.tokens.get(self.index) {
+            Some((_, line)) => *line,
+            None => self.tokens.last().map_or(1, |(_, line)| *line),
+        }
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, SchemaParseError> {
+        Err(SchemaParseError::new(self.line(), message))
+    }
+
+    fn next(&mut self, expected: &str) -> Result<Token, SchemaParseError> {
+        match self.tokens.get(self.index) {
+            Some((token, _)) => {
+                self.index += 1;
+                Ok(token.clone())
+            }
+            None => self.error(format!("Expected {}, but the file ended", expected)),
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), SchemaParseError> {
+        match self.next(&format!("'{}'", symbol))? {
+            Token::Symbol(found) if found == symbol => Ok(()),
+            other => {
+                self.index -= 1;
+                self.error(format!("Expected '{}', but found {:?}", symbol, other))
+            }
+        }
+    }
+
+    fn next_identifier(&mut self, expected: &str) -> Result<String, SchemaParseError> {
+        match self.next(expected)? {
+            Token::Identifier(identifier) => Ok(identifier),
+            other => {
+                self.index -= 1;
+                self.error(format!("Expected {}, but found {:?}", expected, other))
+            }
+        }
+    }
+
+    fn next_max_length(&mut self) -> Result<usize, SchemaParseError> {
+        self.expect_symbol('(')?;
+        let max_length = match self.next("a maximum length")? {
+            Token::Number(max_length) if max_length <= i32::MAX as usize => max_length,
+            other => {
+                self.index -= 1;
+                return self.error(format!(
+                    "Expected a maximum length of at most {}, but found {:?}",
+                    i32::MAX,
+                    other
+                ));
+            }
+        };
+        self.expect_symbol(')')?;
+        Ok(max_length)
+    }
+
+    fn parse_type(&mut self) -> Result<FieldType, SchemaParseError> {
+        let name = self.next_identifier("a type")?;
+        Ok(match name.as_str() {
+            "bool" => FieldType::Bool,
+            "i8" => FieldType::I8,
+            "i16" => FieldType::I16,
+            "char" => FieldType::Char,
+            "i32" => FieldType::I32,
+            "i64" => FieldType::I64,
+            "string" => FieldType::String(self.next_max_length()?),
+            "option" => {
+                self.expect_symbol('<')?;
+                let element = self.parse_type()?;
+                // Java can't distinguish between these and a single null value
+                match element {
+                    FieldType::Option(_) => {
+                        return self.error("Nested options are not supported".to_string())
+                    }
+                    FieldType::String(_) => {
+                        return self.error(
+                            "Strings can already be None, so they can't be in an option"
+                                .to_string(),
+                        )
+                    }
+                    _ => {}
+                }
+                self.expect_symbol('>')?;
+                FieldType::Option(Box::new(element))
+            }
+            "list" => {
+                self.expect_symbol('<')?;
+                let element = self.parse_type()?;
+                self.expect_symbol('>')?;
+                FieldType::List(Box::new(element), self.next_max_length()?)
+            }
+            _ => FieldType::Message(name),
+        })
+    }
+
+    fn parse_message(&mut self) -> Result<MessageDefinition, SchemaParseError> {
+        let keyword = self.next_identifier("'message'")?;
+        if keyword != "message" {
+            self.index -= 1;
+            return self.error(format!("Expected 'message', but found {}", keyword));
+        }
+        let name = self.next_identifier("the name of the message")?;
+        if RESERVED_NAMES.contains(&name.as_str())
+            || RESERVED_MESSAGE_NAMES.contains(&name.as_str())
+        {
+            self.index -= 1;
+            return self.error(format!("{} is a reserved name", name));
+        }
+        self.expect_symbol('{')?;
+        let mut fields: Vec<FieldDefinition> = Vec::new();
+        while self.tokens.get(self.index).map(|(token, _)| token) != Some(&Token::Symbol('}')) {
+            let field_name = self.next_identifier("a field name or '}'")?;
+            if RESERVED_NAMES.contains(&field_name.as_str()) {
+                self.index -= 1;
+                return self.error(format!("{} is a reserved name", field_name));
+            }
+            if fields.iter().any(|field| field.name == field_name) {
+                self.index -= 1;
+                return self.error(format!("{} has 2 fields named {}", name, field_name));
+            }
+            self.expect_symbol(':')?;
+            let field_type = self.parse_type()?;
+            self.expect_symbol(';')?;
+            fields.push(FieldDefinition {
+                name: field_name,
+                field_type,
+            });
+        }
+        self.expect_symbol('}')?;
+        Ok(MessageDefinition { name, fields })
+    }
+}
+
+/**
+ * Parses the given schema file (see SchemaFile for the syntax). Besides syntax errors, an error will be
+ * returned if a message is defined twice, if a field uses an unknown message or if a message contains itself
+ * (other than via a list), because such a message would be infinitely large. An error will also be returned if
+ * the name of a field or message is a keyword of Rust or java, or if the name of a message is the name of a type
+ * that the generated code uses (like List or Option).
+ */
+pub fn parse_schema(source: &str) -> Result<SchemaFile, SchemaParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        index: 0,
+    };
+    let mut messages: Vec<MessageDefinition> = Vec::new();
+    let mut message_lines = Vec::new();
+    while parser.index < parser.tokens.len() {
+        let line = parser.line();
+        let message = parser.parse_message()?;
+        if messages.iter().any(|other| other.name == message.name) {
+            return Err(SchemaParseError::new(
+                line,
+                format!("The message {} is defined twice", message.name),
+            ));
+        }
+        messages.push(message);
+        message_lines.push(line);
+    }
+
+    let schema = SchemaFile { messages };
+    for (message, line) in schema.messages.iter().zip(message_lines) {
+        for field in &message.fields {
+            if let Some(unknown) = schema.find_unknown_message(&field.field_type) {
+                return Err(SchemaParseError::new(
+                    line,
+                    format!(
+                        "The field {}.{} uses the unknown message {}",
+                        message.name, field.name, unknown
+                    ),
+                ));
+            }
+        }
+        if schema.contains_directly(&message.name, message, &mut Vec::new()) {
+            return Err(SchemaParseError::new(
+                line,
+                format!("The message {} contains itself", message.name),
+            ));
+        }
+    }
+    Ok(schema)
+}
+
+impl SchemaFile {
+    fn message(&self, name: &str) -> &MessageDefinition {
+        self.messages
+            .iter()
+            .find(|message| message.name == name)
+            .expect("The messages should have been validated")
+    }
+
+    fn find_unknown_message<'a>(&self, field_type: &'a FieldType) -> Option<&'a str> {
+        match field_type {
+            FieldType::Message(name) => {
+                if self.messages.iter().any(|message| &message.name == name) {
+                    None
+                } else {
+                    Some(name)
+                }
+            }
+            FieldType::Option(element) | FieldType::List(element, _) => {
+                self.find_unknown_message(element)
+            }
+            _ => None,
+        }
+    }
+
+    /**
+     * Checks whether the given message contains the message with the target name, without a list in between.
+     */
+    fn contains_directly<'a>(
+        &'a self,
+        target: &str,
+        message: &'a MessageDefinition,
+        visited: &mut Vec<&'a str>,
+    ) -> bool {
+        if visited.contains(&message.name.as_str()) {
+            return false;
+        }
+        visited.push(&message.name);
+        message.fields.iter().any(|field| {
+            let mut field_type = &field.field_type;
+            if let FieldType::Option(element) = field_type {
+                field_type = element;
+            }
+            match field_type {
+                FieldType::Message(name) => {
+                    name == target || self.contains_directly(target, self.message(name), visited)
+                }
+                _ => false,
+            }
+        })
+    }
+
+    /**
+     * Generates Rust code with a struct for every message. Every struct gets a write method that writes it to a
+     * BitOutput and a read function that reads it from a BitInput. The crate_path is the path of this crate in
+     * the crate that will use the generated code, which is normally bit_helper.
+     */
+    pub fn generate_rust(&self, crate_path: &str) -> String {
+        let mut code = CodeWriter::new();
+        code.line("// Generated by bit_helper from a schema file. Do not edit this file manually.");
+        code.line("");
+        code.line(&format!("use {}::input::*;", crate_path));
+        code.line(&format!("use {}::output::*;", crate_path));
+        for message in &self.messages {
+            code.line("");
+            code.line("#[derive(Clone, Debug, PartialEq, Eq)]");
+            code.open(&format!("pub struct {} {{", message.name));
+            for field in &message.fields {
+                code.line(&format!(
+                    "pub {}: {},",
+                    field.name,
+                    rust_type(&field.field_type)
+                ));
+            }
+            code.close("}");
+            code.line("");
+            code.open(&format!("impl {} {{", message.name));
+
+            code.open("pub fn write(&self, output: &mut dyn BitOutput) {");
+            for field in &message.fields {
+                let value = format!("self.{}", field.name);
+                write_rust_value(&mut code, &field.field_type, &value, false, 0);
+            }
+            code.close("}");
+            code.line("");
+
+            code.open(&format!(
+                "pub fn read(input: &mut dyn BitInput) -> Result<{}, BitInputError> {{",
+                message.name
+            ));
+            for field in &message.fields {
+                let target = format!("field_{}", field.name);
+                read_rust_value(&mut code, &field.field_type, &target, 0);
+            }
+            code.open(&format!("Ok({} {{", message.name));
+            for field in &message.fields {
+                code.line(&format!("{}: field_{},", field.name, field.name));
+            }
+            code.close("})");
+            code.close("}");
+            code.close("}");
+        }
+        code.finish()
+    }
+
+    /**
+     * Generates a java source file with a public static nested class for every message. Every class gets a
+     * write method that writes it to a BitOutput and a static read method that reads it from a BitInput, using
+     * the java BitHelper library. The outer class has the given name and is put in the given package (or in the
+     * default package if the package is empty). This method will panic if a message has the same name as the
+     * outer class, because java doesn't allow nested classes with the name of their outer class.
+     */
+    pub fn generate_java(&self, package: &str, outer_class: &str) -> String {
+        if self
+            .messages
+            .iter()
+            .any(|message| message.name == outer_class)
+        {
+            panic!(
+                "The outer class {} has the same name as one of the messages",
+                outer_class
+            );
+        }
+        let mut code = CodeWriter::new();
+        code.line("// Generated by bit_helper from a schema file. Do not edit this file manually.");
+        if !package.is_empty() {
+            code.line(&format!("package {};", package));
+        }
+        code.line("");
+        code.line("import java.util.ArrayList;");
+        code.line("import java.util.List;");
+        code.line("");
+        code.line("import nl.knokko.util.bits.BitInput;");
+        code.line("import nl.knokko.util.bits.BitOutput;");
+        code.line("");
+        code.open(&format!("public final class {} {{", outer_class));
+        code.line("");
+        code.line(&format!("private {}() {{}}", outer_class));
+        for message in &self.messages {
+            code.line("");
+            code.open(&format!("public static class {} {{", message.name));
+            code.line("");
+            for field in &message.fields {
+                code.line(&format!(
+                    "public {} {};",
+                    java_type(&field.field_type, false),
+                    field.name
+                ));
+            }
+            code.line("");
+
+            code.open("public void write(BitOutput output) {");
+            for field in &message.fields {
+                let value = format!("this.{}", field.name);
+                write_java_value(&mut code, &field.field_type, &value, 0);
+            }
+            code.close("}");
+            code.line("");
+
+            code.open(&format!(
+                "public static {} read(BitInput input) {{",
+                message.name
+            ));
+            code.line(&format!(
+                "{} result = new {}();",
+                message.name, message.name
+            ));
+            let mut variable_count = 0;
+            for field in &message.fields {
+                let target = format!("field_{}", field.name);
+                read_java_value(&mut code, &field.field_type, &target, &mut variable_count);
+                code.line(&format!("result.{} = {};", field.name, target));
+            }
+            code.line("return result;");
+            code.close("}");
+            code.close("}");
+        }
+        code.close("}");
+        code.finish()
+    }
+}
+
+/**
+ * Builds the generated code line by line and takes care of the indentation.
+ */
+struct CodeWriter {
+    code: String,
+    indentation: usize,
+}
+
+impl CodeWriter {
+    fn new() -> CodeWriter {
+        CodeWriter {
+            code: String::new(),
+            indentation: 0,
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        if !line.is_empty() {
+            for _ in 0..self.indentation {
+                self.code.push_str("    ");
+            }
+            self.code.push_str(line);
+        }
+        self.code.push('\n');
+    }
+
+    fn open(&mut self, line: &str) {
+        self.line(line);
+        self.indentation += 1;
+    }
+
+    fn close(&mut self, line: &str) {
+        self.indentation -= 1;
+        self.line(line);
+    }
+
+    fn finish(self) -> String {
+        self.code
+    }
+}
+
+fn rust_type(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Bool => "bool".to_string(),
+        FieldType::I8 => "i8".to_string(),
+        FieldType::I16 => "i16".to_string(),
+        FieldType::Char => "u16".to_string(),
+        FieldType::I32 => "i32".to_string(),
+        FieldType::I64 => "i64".to_string(),
+        FieldType::String(_) => "Option<String>".to_string(),
+        FieldType::Message(name) => name.clone(),
+        FieldType::Option(element) => format!("Option<{}>", rust_type(element)),
+        FieldType::List(element, _) => format!("Vec<{}>", rust_type(element)),
+    }
+}
+
+/**
+ * Writes the code that writes the value of the given type. The value is either a field of self (like
+ * self.name) or a variable that is a reference to the value (when is_reference is true). The depth is used to
+ * give the variables of nested options and lists unique names.
+ */
+fn write_rust_value(
+    code: &mut CodeWriter,
+    field_type: &FieldType,
+    value: &str,
+    is_reference: bool,
+    depth: usize,
+) {
+    let (copy, borrow) = if is_reference {
+        (format!("*{}", value), value.to_string())
+    } else {
+        (value.to_string(), format!("&{}", value))
+    };
+    match field_type {
+        FieldType::Bool => code.line(&format!("output.add_bool({});", copy)),
+        FieldType::I8 => code.line(&format!("output.add_i8({});", copy)),
+        FieldType::I16 => code.line(&format!("output.add_i16({});", copy)),
+        FieldType::Char => code.line(&format!("output.add_u16({});", copy)),
+        FieldType::I32 => code.line(&format!("output.add_i32({});", copy)),
+        FieldType::I64 => code.line(&format!("output.add_i64({});", copy)),
+        FieldType::String(_) => code.line(&format!("output.add_str({}.as_deref());", value)),
+        FieldType::Message(_) => code.line(&format!("{}.write(output);", value)),
+        FieldType::Option(element) => {
+            let inner = format!("value_{}", depth);
+            code.line(&format!("output.add_bool({}.is_some());", value));
+            code.open(&format!("if let Some({}) = {} {{", inner, borrow));
+            write_rust_value(code, element, &inner, true, depth + 1);
+            code.close("}");
+        }
+        FieldType::List(element, _) => {
+            let inner = format!("element_{}", depth);
+            code.line(&format!("output.add_i32({}.len() as i32);", value));
+            code.open(&format!("for {} in {} {{", inner, borrow));
+            write_rust_value(code, element, &inner, true, depth + 1);
+            code.close("}");
+        }
+    }
+}
+
+/**
+ * Writes the code that reads a value of the given type and stores it in a new variable with the given name.
+ */
+fn read_rust_value(code: &mut CodeWriter, field_type: &FieldType, target: &str, depth: usize) {
+    match field_type {
+        FieldType::Bool => code.line(&format!("let {} = input.read_bool()?;", target)),
+        FieldType::I8 => code.line(&format!("let {} = input.read_i8()?;", target)),
+        FieldType::I16 => code.line(&format!("let {} = input.read_i16()?;", target)),
+        FieldType::Char => code.line(&format!("let {} = input.read_u16()?;", target)),
+        FieldType::I32 => code.line(&format!("let {} = input.read_i32()?;", target)),
+        FieldType::I64 => code.line(&format!("let {} = input.read_i64()?;", target)),
+        FieldType::String(max_length) => code.line(&format!(
+            "let {} = input.read_string({})?;",
+            target, max_length
+        )),
+        FieldType::Message(name) => code.line(&format!("let {} = {}::read(input)?;", target, name)),
+        FieldType::Option(element) => {
+            let inner = format!("value_{}", depth);
+            code.open(&format!("let {} = if input.read_bool()? {{", target));
+            read_rust_value(code, element, &inner, depth + 1);
+            code.line(&format!("Some({})", inner));
+            code.close("} else {");
+            code.indentation += 1;
+            code.line("None");
+            code.close("};");
+        }
+        FieldType::List(element, max_length) => {
+            let length = format!("length_{}", depth);
+            let inner = format!("element_{}", depth);
+            // A negative length becomes larger than any allowed maximum when it is converted to a u32
+            code.line(&format!("let {} = input.read_i32()? as u32;", length));
+            code.open(&format!("if {} as usize > {} {{", length, max_length));
+            code.line(&format!(
+                "return Err(BitInputError::Length(LengthError::new({} as u64, {})));",
+                length, max_length
+            ));
+            code.close("}");
+            code.line(&format!(
+                "let mut {} = Vec::with_capacity({} as usize);",
+                target, length
+            ));
+            code.open(&format!("for _ in 0..{} {{", length));
+            read_rust_value(code, element, &inner, depth + 1);
+            code.line(&format!("{}.push({});", target, inner));
+            code.close("}");
+        }
+    }
+}
+
+/**
+ * Returns the java type for the given field type. Primitive types are boxed when they are used as type
+ * argument or when they can be null (in an option).
+ */
+fn java_type(field_type: &FieldType, boxed: bool) -> String {
+    let (primitive, boxed_type) = match field_type {
+        FieldType::Bool => ("boolean", "Boolean"),
+        FieldType::I8 => ("byte", "Byte"),
+        FieldType::I16 => ("short", "Short"),
+        FieldType::Char => ("char", "Character"),
+        FieldType::I32 => ("int", "Integer"),
+        FieldType::I64 => ("long", "Long"),
+        FieldType::String(_) => return "String".to_string(),
+        FieldType::Message(name) => return name.clone(),
+        FieldType::Option(element) => return java_type(element, true),
+        FieldType::List(element, _) => return format!("List<{}>", java_type(element, true)),
+    };
+    if boxed {
+        boxed_type.to_string()
+    } else {
+        primitive.to_string()
+    }
+}
+
+fn write_java_value(code: &mut CodeWriter, field_type: &FieldType, value: &str, depth: usize) {
+    match field_type {
+        FieldType::Bool => code.line(&format!("output.addBoolean({});", value)),
+        FieldType::I8 => code.line(&format!("output.addByte({});", value)),
+        FieldType::I16 => code.line(&format!("output.addShort({});", value)),
+        FieldType::Char => code.line(&format!("output.addChar({});", value)),
+        FieldType::I32 => code.line(&format!("output.addInt({});", value)),
+        FieldType::I64 => code.line(&format!("output.addLong({});", value)),
+        FieldType::String(_) => code.line(&format!("output.addString({});", value)),
+        FieldType::Message(_) => code.line(&format!("{}.write(output);", value)),
+        FieldType::Option(element) => {
+            code.line(&format!("output.addBoolean({} != null);", value));
+            code.open(&format!("if ({} != null) {{", value));
+            write_java_value(code, element, value, depth + 1);
+            code.close("}");
+        }
+        FieldType::List(element, _) => {
+            let inner = format!("element_{}", depth);
+            code.line(&format!("output.addInt({}.size());", value));
+            code.open(&format!(
+                "for ({} {} : {}) {{",
+                java_type(element, true),
+                inner,
+                value
+            ));
+            write_java_value(code, element, &inner, depth + 1);
+            code.close("}");
+        }
+    }
+}
+
+/**
+ * Writes the code that reads a value of the given type and stores it in a new variable with the given name. Java
+ * doesn't allow local variables to hide each other, so the temporary variables are numbered with variable_count.
+ */
+fn read_java_value(
+    code: &mut CodeWriter,
+    field_type: &FieldType,
+    target: &str,
+    variable_count: &mut usize,
+) {
+    let java_type = java_type(field_type, false);
+    match field_type {
+        FieldType::Bool => code.line(&format!("{} {} = input.readBoolean();", java_type, target)),
+        FieldType::I8 => code.line(&format!("{} {} = input.readByte();", java_type, target)),
+        FieldType::I16 => code.line(&format!("{} {} = input.readShort();", java_type, target)),
+        FieldType::Char => code.line(&format!("{} {} = input.readChar();", java_type, target)),
+        FieldType::I32 => code.line(&format!("{} {} = input.readInt();", java_type, target)),
+        FieldType::I64 => code.line(&format!("{} {} = input.readLong();", java_type, target)),
+        FieldType::String(max_length) => {
+            code.line(&format!("String {} = input.readString();", target));
+            code.open(&format!(
+                "if ({} != null && {}.length() > {}) {{",
+                target, target, max_length
+            ));
+            code.line(&format!(
+                "throw new IllegalArgumentException(\"The string is longer than {}\");",
+                max_length
+            ));
+            code.close("}");
+        }
+        FieldType::Message(name) => {
+            code.line(&format!("{} {} = {}.read(input);", name, target, name))
+        }
+        FieldType::Option(element) => {
+            let inner = format!("value_{}", variable_count);
+            *variable_count += 1;
+            code.line(&format!("{} {} = null;", java_type, target));
+            code.open("if (input.readBoolean()) {");
+            read_java_value(code, element, &inner, variable_count);
+            code.line(&format!("{} = {};", target, inner));
+            code.close("}");
+        }
+        FieldType::List(element, max_length) => {
+            let length = format!("length_{}", variable_count);
+            let index = format!("index_{}", variable_count);
+            let inner = format!("element_{}", variable_count);
+            *variable_count += 1;
+            code.line(&format!("int {} = input.readInt();", length));
+            code.open(&format!(
+                "if ({} < 0 || {} > {}) {{",
+                length, length, max_length
+            ));
+            code.line(&format!(
+                "throw new IllegalArgumentException(\"Invalid list length \" + {});",
+                length
+            ));
+            code.close("}");
+            code.line(&format!(
+                "{} {} = new ArrayList<>({});",
+                java_type, target, length
+            ));
+            code.open(&format!(
+                "for (int {} = 0; {} < {}; {}++) {{",
+                index, index, length, index
+            ));
+            read_java_value(code, element, &inner, variable_count);
+            code.line(&format!("{}.add({});", target, inner));
+            code.close("}");
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use crate::checksum::*;
+use crate::input::*;
+use crate::output::*;
+
+/**
+ * A BitOutput for the payload of a container. A container is a self-describing byte format for data that is
+ * stored for a longer time, like save files. It consists of:
+ * - the magic bytes, which identify the application or file type
+ * - the format version (stored with add_var_u64)
+ * - the flags (stored with add_var_u64), which the application can use for anything it likes, for instance
+ *   to tell whether optional parts are present
+ * - the number of bits of the payload (stored with add_var_u64), followed by padding to the next byte boundary
+ * - the payload bytes
+ * - the CRC-32 of all bytes before it, stored as 4 big-endian bytes
+ *
+ * The data that is written to this output becomes the payload. The container bytes are created by finish and
+ * can be read with a ContainerReader.
+ */
+pub struct ContainerWriter {
+    magic: Vec<u8>,
+    version: u64,
+    flags: u64,
+    payload: U8VecBitOutput,
+}
+
+impl ContainerWriter {
+    /**
+     * Creates a new ContainerWriter with the given magic bytes, format version and flags.
+     */
+    pub fn new(magic: &[u8], version: u64, flags: u64) -> ContainerWriter {
+        ContainerWriter {
+            magic: magic.to_vec(),
+            version,
+            flags,
+            payload: U8VecBitOutput::with_capacity(0),
+        }
+    }
+
+    /**
+     * Returns the bytes of the container with the payload that has been written so far.
+     */
+    pub fn finish(self) -> Vec<u8> {
+        let mut header = U8VecBitOutput::with_capacity(0);
+        header.add_var_u64(self.version);
+        header.add_var_u64(self.flags);
+        header.add_var_u64(self.payload.bit_len() as u64);
+        let (header_bytes, _) = header.into_bytes_with_bit_len();
+        let (payload_bytes, _) = self.payload.into_bytes_with_bit_len();
+
+        let mut result =
+            Vec::with_capacity(self.magic.len() + header_bytes.len() + payload_bytes.len() + 4);
+        result.extend_from_slice(&self.magic);
+        result.extend_from_slice(&header_bytes);
+        result.extend_from_slice(&payload_bytes);
+        let checksum = crc32(&result);
+        result.extend_from_slice(&checksum.to_be_bytes());
+        result
+    }
+}
+
+impl BitOutput for ContainerWriter {
+    fn add_direct_bool(&mut self, value: bool) {
+        self.payload.add_direct_bool(value);
+    }
+
+    fn add_direct_i8(&mut self, value: i8) {
+        self.payload.add_direct_i8(value);
+    }
+
+    fn ensure_extra_capacity(&mut self, extra_bools: usize) {
+        self.payload.ensure_extra_capacity(extra_bools);
+    }
+
+    fn terminate(&mut self) {
+        // The payload is only complete when finish is called, so there is nothing to shrink yet
+    }
+
+    fn bit_len(&self) -> usize {
+        self.payload.bit_len()
+    }
+}
+
+/**
+ * Reads a container that was created by a ContainerWriter. The magic bytes, the checksum and the header are
+ * checked when the reader is created, so the version and flags can be inspected before the payload is
+ * decoded. This allows the application to choose how to decode (or migrate) the payload, based on its version.
+ */
+pub struct ContainerReader {
+    bytes: Vec<u8>,
+    version: u64,
+    flags: u64,
+    payload_start_bit: usize,
+    payload_bit_len: usize,
+}
+
+impl ContainerReader {
+    /**
+     * Opens the container in the given bytes. If the bytes don't start with the given magic bytes, an
+     * InvalidMagicError will be returned. If the checksum doesn't match, a ChecksumMismatchError will be
+     * returned. If the header is inconsistent with the size of the container, an InvalidCodeError will be
+     * returned.
+     */
+    pub fn open(bytes: Vec<u8>, magic: &[u8]) -> Result<ContainerReader, BitInputError> {
+        if !bytes.starts_with(magic) {
+            return Err(BitInputError::InvalidMagic(InvalidMagicError));
+        }
+        if bytes.len() < magic.len() + 4 {
+            return Err(BitInputError::InvalidCode(InvalidCodeError));
+        }
+        let checksum_index = bytes.len() - 4;
+        let mut stored_checksum = [0; 4];
+        stored_checksum.copy_from_slice(&bytes[checksum_index..]);
+        let stored_checksum = u32::from_be_bytes(stored_checksum);
+        let computed_checksum = crc32(&bytes[..checksum_index]);
+        if stored_checksum != computed_checksum {
+            return Err(BitInputError::ChecksumMismatch(ChecksumMismatchError::new(
+                stored_checksum,
+                computed_checksum,
+            )));
+        }
+
+        let mut header =
+            U8VecRefBitInput::with_bit_range(&bytes, 8 * magic.len(), 8 * checksum_index);
+        let version = header.read_var_u64()?;
+        let flags = header.read_var_u64()?;
+        let payload_bit_len = header.read_var_u64()?;
+        let payload_start_bit = header.read_index().div_ceil(8) * 8;
+        if payload_bit_len.div_ceil(8) != ((8 * checksum_index - payload_start_bit) / 8) as u64 {
+            return Err(BitInputError::InvalidCode(InvalidCodeError));
+        }
+
+        Ok(ContainerReader {
+            bytes,
+            version,
+            flags,
+            payload_start_bit,
+            payload_bit_len: payload_bit_len as usize,
+        })
+    }
+
+    /**
+     * Returns the format version of the container.
+     */
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /**
+     * Returns the flags of the container.
+     */
+    pub fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    /**
+     * Returns the number of bits of the payload.
+     */
+    pub fn payload_bit_len(&self) -> usize {
+        self.payload_bit_len
+    }
+
+    /**
+     * Returns a bit input that reads the payload. It can't read beyond the end of the payload.
+     */
+    pub fn payload(&self) -> U8VecRefBitInput<'_> {
+        U8VecRefBitInput::with_bit_range(
+            &self.bytes,
+            self.payload_start_bit,
+            self.payload_start_bit + self.payload_bit_len,
+        )
+    }
+}
@@ -144,13 +144,13 @@ pub fn bools_to_sized_i64(bits: usize, bools: &[bool], start_index: usize) -> i6
     integer
 }
 
-fn check_unsigned_bitcount(size_bits: usize) {
+pub(crate) fn check_unsigned_bitcount(size_bits: usize) {
     if size_bits > 64 {
         panic!("You can't use more than 64 bits to store the magnitude of an unsigned integer, but you are using {} bits", size_bits);
     }
 }
 
-fn check_unsigned_overflow(number: u64, size_bits: usize) {
+pub(crate) fn check_unsigned_overflow(number: u64, size_bits: usize) {
     if size_bits != 64 && (POWERS[size_bits] <= number) {
         panic!(
             "The magnitude of the integer {} can't be stored using only {} bits.",
@@ -0,0 +1,579 @@
+use crate::bit_vec::*;
+use crate::converter::*;
+use crate::input::*;
+use crate::output::*;
+
+/**
+ * A BitOutput that wraps another BitOutput and protects the data that is written to it with an extended
+ * Hamming(7,4) code (also known as SECDED: single error correction, double error detection). Every 4 bits of
+ * data are stored as a codeword of 8 bits, so the data will take twice as many bits. A HammingBitInput can
+ * correct 1 flipped bit in every codeword and detect 2 flipped bits in the same codeword.
+ *
+ * Like ChecksummedBitOutput, the data is buffered until terminate (or finish) is called. The data is then
+ * written to the wrapped output as a section: the number of bits of the data (stored with add_var_u64) and the
+ * data itself, padded with zeros to a multiple of 4 bits, are encoded together. Since the length is encoded as
+ * well, it is protected against bit flips too.
+ */
+pub struct HammingBitOutput<O: BitOutput> {
+    inner: O,
+    data: BitVec,
+}
+
+impl<O: BitOutput> HammingBitOutput<O> {
+    /**
+     * Creates a new HammingBitOutput that will write its section to the given output.
+     */
+    pub fn new(inner: O) -> HammingBitOutput<O> {
+        HammingBitOutput {
+            inner,
+            data: BitVec::new(),
+        }
+    }
+
+    /**
+     * Writes the section to the wrapped output without terminating it and returns the wrapped output.
+     */
+    pub fn finish(mut self) -> O {
+        self.write_section();
+        self.inner
+    }
+
+    /**
+     * Returns the wrapped output. This should only be used after terminate has been called, because the data of
+     * the current section will be lost otherwise.
+     */
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+
+    fn write_section(&mut self) {
+        let mut stream = BitVec::with_capacity(self.data.len() + 70);
+        stream.add_var_u64(self.data.len() as u64);
+        stream.extend_from_bit_vec(&self.data);
+        while stream.len() & 3 != 0 {
+            stream.push(false);
+        }
+
+        self.inner.ensure_extra_capacity(2 * stream.len());
+        for nibble_index in 0..stream.len() / 4 {
+            let start = 4 * nibble_index;
+            let codeword = encode_hamming([
+                stream.get(start),
+                stream.get(start + 1),
+                stream.get(start + 2),
+                stream.get(start + 3),
+            ]);
+            self.inner.add_direct_bools_from_slice(&codeword);
+        }
+        self.data.clear();
+    }
+}
+
+impl<O: BitOutput> BitOutput for HammingBitOutput<O> {
+    fn add_direct_bool(&mut self, value: bool) {
+        self.data.push(value);
+    }
+
+    fn add_direct_i8(&mut self, value: i8) {
+        self.data.add_direct_i8(value);
+    }
+
+    fn ensure_extra_capacity(&mut self, extra_bools: usize) {
+        self.data.reserve(extra_bools);
+    }
+
+    /**
+     * Writes the encoded section to the wrapped output and terminates the wrapped output. This should be called
+     * exactly once.
+     */
+    fn terminate(&mut self) {
+        self.write_section();
+        self.inner.terminate();
+    }
+
+    fn bit_len(&self) -> usize {
+        self.data.len()
+    }
+}
+
+/**
+ * Encodes 4 data bits as an extended Hamming codeword. The element at index 0 is the overall parity bit and
+ * the elements at index 1 to 7 are the positions of the Hamming(7,4) code: the parity bits are at the
+ * positions 1, 2 and 4 and the data bits are at the positions 3, 5, 6 and 7.
+ */
+fn encode_hamming(data: [bool; 4]) -> [bool; 8] {
+    let mut codeword = [false; 8];
+    codeword[3] = data[0];
+    codeword[5] = data[1];
+    codeword[6] = data[2];
+    codeword[7] = data[3];
+    codeword[1] = data[0] ^ data[1] ^ data[3];
+    codeword[2] = data[0] ^ data[2] ^ data[3];
+    codeword[4] = data[1] ^ data[2] ^ data[3];
+    codeword[0] = codeword[1..].iter().fold(false, |parity, bit| parity ^ bit);
+    codeword
+}
+
+/**
+ * Decodes an extended Hamming codeword and returns the 4 data bits and whether an error was corrected. If the
+ * codeword has 2 errors, an UncorrectableError will be returned.
+ */
+fn decode_hamming(mut codeword: [bool; 8]) -> Result<([bool; 4], bool), UncorrectableError> {
+    let mut syndrome = 0;
+    for (position, bit) in codeword.iter().enumerate() {
+        if *bit {
+            syndrome ^= position;
+        }
+    }
+    let parity_error = codeword.iter().fold(false, |parity, bit| parity ^ bit);
+    let corrected = if parity_error {
+        // An odd number of errors: assume that only the bit at the syndrome position was flipped. If the
+        // syndrome is 0, the overall parity bit itself was flipped.
+        codeword[syndrome] = !codeword[syndrome];
+        true
+    } else if syndrome != 0 {
+        return Err(UncorrectableError);
+    } else {
+        false
+    };
+    Ok((
+        [codeword[3], codeword[5], codeword[6], codeword[7]],
+        corrected,
+    ))
+}
+
+/**
+ * A BitInput that reads a section that was written by a HammingBitOutput. The whole section is decoded when
+ * this input is created: every codeword with 1 flipped bit is corrected and if any codeword has 2 flipped bits,
+ * an UncorrectableError will be returned. This input can only read the data of the section.
+ */
+pub struct HammingBitInput {
+    data: BitVec,
+    read_index: usize,
+    bound_index: usize,
+    corrected_errors: usize,
+}
+
+impl HammingBitInput {
+    /**
+     * Reads and decodes the next section from the given input. If the section has more than max_bits bits of
+     * data, a LengthError will be returned instead of allocating memory for the data.
+     */
+    pub fn new(
+        input: &mut dyn BitInput,
+        max_bits: usize,
+    ) -> Result<HammingBitInput, BitInputError> {
+        let mut result = HammingBitInput {
+            data: BitVec::new(),
+            read_index: 0,
+            bound_index: 0,
+            corrected_errors: 0,
+        };
+
+        // The length is encoded as well, so decode codewords until it can be read
+        let (bit_count, header_bits) = loop {
+            let mut header = BitVecBitInput::new(&result.data);
+            match read_length(&mut header, max_bits) {
+                Ok(bit_count) => break (bit_count, header.read_index()),
+                Err(BitInputError::InputCapacity(_)) => result.decode_codeword(input)?,
+                Err(error) => return Err(error),
+            }
+        };
+
+        let total_bits = (header_bits + bit_count).div_ceil(4) * 4;
+        input.ensure_extra_capacity(2 * (total_bits - result.data.len()))?;
+        result.data.reserve(total_bits - result.data.len());
+        while result.data.len() < total_bits {
+            result.decode_codeword(input)?;
+        }
+        result.read_index = header_bits;
+        result.bound_index = header_bits + bit_count;
+        Ok(result)
+    }
+
+    fn decode_codeword(&mut self, input: &mut dyn BitInput) -> Result<(), BitInputError> {
+        let mut codeword = [false; 8];
+        input.read_bools_to_slice(&mut codeword, 0, 8)?;
+        let (bits, corrected) = decode_hamming(codeword).map_err(BitInputError::Uncorrectable)?;
+        if corrected {
+            self.corrected_errors += 1;
+        }
+        self.data.extend(bits.iter().copied());
+        Ok(())
+    }
+
+    /**
+     * Returns the number of codewords of the section that had a flipped bit and were corrected.
+     */
+    pub fn corrected_errors(&self) -> usize {
+        self.corrected_errors
+    }
+}
+
+impl BitInput for HammingBitInput {
+    fn read_direct_bool(&mut self) -> bool {
+        let result = self.data.get(self.read_index);
+        self.read_index += 1;
+        result
+    }
+
+    fn read_direct_i8(&mut self) -> i8 {
+        let mut bools = [false; 8];
+        for value in bools.iter_mut() {
+            *value = self.read_direct_bool();
+        }
+        bool_array_to_i8(bools)
+    }
+
+    fn ensure_extra_capacity(&mut self, extra_bools: usize) -> Result<(), InputCapacityError> {
+        if self.read_index + extra_bools > self.bound_index {
+            Err(InputCapacityError::new(
+                self.read_index,
+                self.bound_index,
+                extra_bools,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn terminate(&mut self) {
+        self.data.clear();
+        self.read_index = 0;
+        self.bound_index = 0;
+    }
+}
+
+// The exponentials and logarithms of GF(256) with the primitive polynomial x^8 + x^4 + x^3 + x^2 + 1. The
+// exponential table is twice as long, so the sum of 2 logarithms can be looked up without taking the modulus.
+const GF_EXP: [u8; 512] = gf_exp_table();
+const GF_LOG: [u8; 256] = gf_log_table();
+
+const fn gf_exp_table() -> [u8; 512] {
+    let mut table = [0; 512];
+    let mut value: u32 = 1;
+    let mut index = 0;
+    while index < 512 {
+        table[index] = value as u8;
+        value <<= 1;
+        if value & 0x100 != 0 {
+            value ^= 0x11D;
+        }
+        index += 1;
+    }
+    table
+}
+
+const fn gf_log_table() -> [u8; 256] {
+    let exp = gf_exp_table();
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 255 {
+        table[exp[index] as usize] = index as u8;
+        index += 1;
+    }
+    table
+}
+
+fn gf_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        GF_EXP[GF_LOG[a as usize] as usize + GF_LOG[b as usize] as usize]
+    }
+}
+
+fn gf_div(a: u8, b: u8) -> u8 {
+    if a == 0 {
+        0
+    } else {
+        GF_EXP[GF_LOG[a as usize] as usize + 255 - GF_LOG[b as usize] as usize]
+    }
+}
+
+fn gf_inverse(value: u8) -> u8 {
+    GF_EXP[255 - GF_LOG[value as usize] as usize]
+}
+
+/**
+ * Returns 2 to the power of the given (possibly negative) exponent.
+ */
+fn gf_pow2(exponent: isize) -> u8 {
+    GF_EXP[exponent.rem_euclid(255) as usize]
+}
+
+// The polynomials below store their coefficients from the highest degree to the lowest degree
+
+fn poly_scale(poly: &[u8], factor: u8) -> Vec<u8> {
+    poly.iter()
+        .map(|coefficient| gf_mul(*coefficient, factor))
+        .collect()
+}
+
+fn poly_add(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let length = a.len().max(b.len());
+    let mut result = vec![0; length];
+    for (index, coefficient) in a.iter().enumerate() {
+        result[index + length - a.len()] = *coefficient;
+    }
+    for (index, coefficient) in b.iter().enumerate() {
+        result[index + length - b.len()] ^= *coefficient;
+    }
+    result
+}
+
+fn poly_mul(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; a.len() + b.len() - 1];
+    for (a_index, a_coefficient) in a.iter().enumerate() {
+        for (b_index, b_coefficient) in b.iter().enumerate() {
+            result[a_index + b_index] ^= gf_mul(*a_coefficient, *b_coefficient);
+        }
+    }
+    result
+}
+
+fn poly_eval(poly: &[u8], x: u8) -> u8 {
+    poly.iter()
+        .fold(0, |result, coefficient| gf_mul(result, x) ^ coefficient)
+}
+
+/**
+ * A Reed-Solomon codec over GF(256) that protects blocks of bytes against errors. Every block consists of at
+ * most 255 bytes: the data bytes followed by parity_bytes parity bytes. The decoder can correct up to
+ * parity_bytes / 2 wrong bytes in every block, no matter how many bits of those bytes were flipped. This makes
+ * it a good choice for burst errors, where many consecutive bits are damaged.
+ *
+ * The block methods work on a single block, while encode and decode split longer data into blocks of
+ * max_data_bytes data bytes (the last block can be shorter).
+ */
+#[derive(Clone, Debug)]
+pub struct ReedSolomon {
+    parity_bytes: usize,
+    generator: Vec<u8>,
+}
+
+impl ReedSolomon {
+    /**
+     * Creates a new ReedSolomon codec that adds parity_bytes parity bytes to every block. This function will
+     * panic if parity_bytes is 0 or larger than 254.
+     */
+    pub fn new(parity_bytes: usize) -> ReedSolomon {
+        if parity_bytes == 0 || parity_bytes > 254 {
+            panic!(
+                "The number of parity bytes must be between 1 and 254, but it is {}",
+                parity_bytes
+            );
+        }
+        let mut generator = vec![1];
+        for index in 0..parity_bytes {
+            generator = poly_mul(&generator, &[1, gf_pow2(index as isize)]);
+        }
+        ReedSolomon {
+            parity_bytes,
+            generator,
+        }
+    }
+
+    /**
+     * Returns the number of parity bytes that is added to every block.
+     */
+    pub fn parity_bytes(&self) -> usize {
+        self.parity_bytes
+    }
+
+    /**
+     * Returns the maximum number of data bytes in a block.
+     */
+    pub fn max_data_bytes(&self) -> usize {
+        255 - self.parity_bytes
+    }
+
+    /**
+     * Encodes a single block: returns the data followed by the parity bytes. This function will panic if data
+     * has more than max_data_bytes bytes.
+     */
+    pub fn encode_block(&self, data: &[u8]) -> Vec<u8> {
+        if data.len() > self.max_data_bytes() {
+            panic!(
+                "A block can have at most {} data bytes, but {} were given",
+                self.max_data_bytes(),
+                data.len()
+            );
+        }
+        let mut block = data.to_vec();
+        block.resize(data.len() + self.parity_bytes, 0);
+        // The parity bytes are the remainder of the division of the data by the generator polynomial
+        for index in 0..data.len() {
+            let coefficient = block[index];
+            if coefficient != 0 {
+                for (offset, generator_coefficient) in self.generator.iter().enumerate().skip(1) {
+                    block[index + offset] ^= gf_mul(*generator_coefficient, coefficient);
+                }
+            }
+        }
+        block[..data.len()].copy_from_slice(data);
+        block
+    }
+
+    /**
+     * Corrects the errors in the given block (data and parity bytes) and returns the number of corrected bytes.
+     * If the block has more errors than can be corrected, an UncorrectableError will be returned and the block
+     * will not be changed. Note that a block with many errors can look like a (different) block with only a few
+     * errors, so it is not guaranteed that too many errors will be detected.
+     */
+    pub fn decode_block(&self, block: &mut [u8]) -> Result<usize, UncorrectableError> {
+        if block.len() <= self.parity_bytes || block.len() > 255 {
+            return Err(UncorrectableError);
+        }
+        let syndromes = self.syndromes(block);
+        if syndromes.iter().all(|syndrome| *syndrome == 0) {
+            return Ok(0);
+        }
+
+        let error_locator = self.error_locator(&syndromes)?;
+        let error_positions = find_error_positions(&error_locator, block.len())?;
+        let mut corrected = block.to_vec();
+        correct_errors(&mut corrected, &syndromes, &error_positions);
+        if self
+            .syndromes(&corrected)
+            .iter()
+            .any(|syndrome| *syndrome != 0)
+        {
+            return Err(UncorrectableError);
+        }
+        block.copy_from_slice(&corrected);
+        Ok(error_positions.len())
+    }
+
+    /**
+     * Splits the data into blocks of max_data_bytes data bytes and returns the encoded blocks, one after
+     * another.
+     */
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let block_count = data.len().div_ceil(self.max_data_bytes());
+        let mut result = Vec::with_capacity(data.len() + block_count * self.parity_bytes);
+        for data_block in data.chunks(self.max_data_bytes()) {
+            result.extend_from_slice(&self.encode_block(data_block));
+        }
+        result
+    }
+
+    /**
+     * Decodes data that was encoded with encode and returns the corrected data and the total number of
+     * corrected bytes. If any block has more errors than can be corrected, an UncorrectableError will be
+     * returned.
+     */
+    pub fn decode(&self, encoded: &[u8]) -> Result<(Vec<u8>, usize), BitInputError> {
+        let mut result = Vec::with_capacity(encoded.len());
+        let mut corrected_bytes = 0;
+        for encoded_block in encoded.chunks(255) {
+            let mut block = encoded_block.to_vec();
+            corrected_bytes += self
+                .decode_block(&mut block)
+                .map_err(BitInputError::Uncorrectable)?;
+            result.extend_from_slice(&block[..block.len() - self.parity_bytes]);
+        }
+        Ok((result, corrected_bytes))
+    }
+
+    /**
+     * Computes the syndromes of the given block, preceded by a 0 to simplify the indexing in error_locator.
+     */
+    fn syndromes(&self, block: &[u8]) -> Vec<u8> {
+        let mut syndromes = vec![0; self.parity_bytes + 1];
+        for (index, syndrome) in syndromes.iter_mut().skip(1).enumerate() {
+            *syndrome = poly_eval(block, gf_pow2(index as isize));
+        }
+        syndromes
+    }
+
+    /**
+     * Computes the error locator polynomial with the Berlekamp-Massey algorithm.
+     */
+    fn error_locator(&self, syndromes: &[u8]) -> Result<Vec<u8>, UncorrectableError> {
+        let mut locator = vec![1];
+        let mut old_locator = vec![1];
+        for index in 1..=self.parity_bytes {
+            let mut delta = syndromes[index];
+            for offset in 1..locator.len() {
+                delta ^= gf_mul(
+                    locator[locator.len() - 1 - offset],
+                    syndromes[index - offset],
+                );
+            }
+            old_locator.push(0);
+            if delta != 0 {
+                if old_locator.len() > locator.len() {
+                    let new_locator = poly_scale(&old_locator, delta);
+                    old_locator = poly_scale(&locator, gf_inverse(delta));
+                    locator = new_locator;
+                }
+                locator = poly_add(&locator, &poly_scale(&old_locator, delta));
+            }
+        }
+        let leading_zeros = locator
+            .iter()
+            .take_while(|coefficient| **coefficient == 0)
+            .count();
+        locator.drain(..leading_zeros);
+        if 2 * (locator.len() - 1) > self.parity_bytes {
+            return Err(UncorrectableError);
+        }
+        Ok(locator)
+    }
+}
+
+/**
+ * Finds the indices of the wrong bytes of the block by searching the roots of the error locator (a Chien
+ * search). If the number of roots doesn't match the degree of the locator, the errors can't be corrected.
+ */
+fn find_error_positions(
+    error_locator: &[u8],
+    block_length: usize,
+) -> Result<Vec<usize>, UncorrectableError> {
+    let reversed_locator: Vec<u8> = error_locator.iter().rev().copied().collect();
+    let positions: Vec<usize> = (0..block_length)
+        .filter(|power| poly_eval(&reversed_locator, gf_pow2(*power as isize)) == 0)
+        .map(|power| block_length - 1 - power)
+        .collect();
+    if positions.len() != error_locator.len() - 1 {
+        return Err(UncorrectableError);
+    }
+    Ok(positions)
+}
+
+/**
+ * Computes the error magnitudes with the Forney algorithm and corrects the bytes at the given positions.
+ */
+fn correct_errors(block: &mut [u8], syndromes: &[u8], error_positions: &[usize]) {
+    let coefficient_positions: Vec<usize> = error_positions
+        .iter()
+        .map(|position| block.len() - 1 - position)
+        .collect();
+    let mut errata_locator = vec![1];
+    for position in &coefficient_positions {
+        errata_locator = poly_mul(&errata_locator, &[gf_pow2(*position as isize), 1]);
+    }
+
+    // The error evaluator is the product of the syndromes and the locator, modulo x^(number of errors + 1)
+    let reversed_syndromes: Vec<u8> = syndromes.iter().rev().copied().collect();
+    let product = poly_mul(&reversed_syndromes, &errata_locator);
+    let error_evaluator = &product[product.len() - errata_locator.len()..];
+
+    let roots: Vec<u8> = coefficient_positions
+        .iter()
+        .map(|position| gf_pow2(*position as isize))
+        .collect();
+    for (index, root) in roots.iter().enumerate() {
+        let root_inverse = gf_inverse(*root);
+        let mut locator_derivative = 1;
+        for (other_index, other_root) in roots.iter().enumerate() {
+            if other_index != index {
+                locator_derivative =
+                    gf_mul(locator_derivative, 1 ^ gf_mul(root_inverse, *other_root));
+            }
+        }
+        let evaluated = gf_mul(*root, poly_eval(error_evaluator, root_inverse));
+        block[error_positions[index]] ^= gf_div(evaluated, locator_derivative);
+    }
+}
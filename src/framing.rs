@@ -0,0 +1,281 @@
+use crate::bit_vec::*;
+use crate::converter::*;
+use crate::input::*;
+use crate::output::*;
+use std::io::{Read, Write};
+
+// A u64 needs at most 10 bytes in LEB128
+const MAX_HEADER_BYTES: usize = 10;
+
+/**
+ * Writes messages as frames to a byte stream, for instance a TcpStream. Every frame consists of a header and
+ * the bytes of the message. The header is the exact number of bits of the message, stored as an unsigned
+ * LEB128 number: 7 bits per byte, starting with the least significant bits, where the most significant bit of
+ * each byte tells whether another byte follows. Because the exact number of bits is stored, the FrameReader
+ * on the other side will not be able to read the padding bits at the end of the last byte.
+ *
+ * The messages can be written to any FrameOutput, for instance a U8VecBitOutput or a BitVec. The frames can be
+ * read again with a FrameReader.
+ */
+pub struct FrameWriter<W: Write> {
+    writer: W,
+    // The header and the message are copied to this buffer, so every frame is written with a single write
+    frame_buffer: Vec<u8>,
+}
+
+impl<W: Write> FrameWriter<W> {
+    /**
+     * Creates a new FrameWriter that writes its frames to the given writer.
+     */
+    pub fn new(writer: W) -> FrameWriter<W> {
+        FrameWriter {
+            writer,
+            frame_buffer: Vec::new(),
+        }
+    }
+
+    /**
+     * Writes all data that has been written to the given output as 1 frame. This method doesn't flush the
+     * underlying writer, so call flush when the frame should be sent immediately.
+     */
+    pub fn write_frame<M: FrameOutput + ?Sized>(&mut self, message: &M) -> Result<(), FrameError> {
+        let mut header = [0; MAX_HEADER_BYTES];
+        let header_len = encode_frame_header(message.bit_len() as u64, &mut header);
+        self.frame_buffer.clear();
+        self.frame_buffer.extend_from_slice(&header[..header_len]);
+        message.append_frame_bytes(&mut self.frame_buffer);
+        self.writer.write_all(&self.frame_buffer)?;
+        Ok(())
+    }
+
+    /**
+     * Flushes the underlying writer.
+     */
+    pub fn flush(&mut self) -> Result<(), FrameError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /**
+     * Returns a mutable reference to the underlying writer.
+     */
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /**
+     * Returns the underlying writer.
+     */
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/**
+ * A BitOutput whose data can be written as a frame by a FrameWriter. It is implemented by U8VecBitOutput,
+ * I8VecBitOutput, BoolVecBitOutput and BitVec.
+ */
+pub trait FrameOutput: BitOutput {
+    /**
+     * Adds the data of this output to the given buffer, using the same bytes as U8VecBitOutput would use for
+     * it. Exactly bit_len().div_ceil(8) bytes must be added.
+     */
+    fn append_frame_bytes(&self, buffer: &mut Vec<u8>);
+}
+
+impl FrameOutput for U8VecBitOutput {
+    fn append_frame_bytes(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.vector[..self.bit_len().div_ceil(8)]);
+    }
+}
+
+impl FrameOutput for I8VecBitOutput {
+    fn append_frame_bytes(&self, buffer: &mut Vec<u8>) {
+        let byte_len = self.bit_len().div_ceil(8);
+        buffer.extend(self.vector[..byte_len].iter().map(|byte| *byte as u8));
+    }
+}
+
+impl FrameOutput for BoolVecBitOutput {
+    fn append_frame_bytes(&self, buffer: &mut Vec<u8>) {
+        append_bools(self.get_slice().iter().copied(), buffer);
+    }
+}
+
+impl FrameOutput for BitVec {
+    fn append_frame_bytes(&self, buffer: &mut Vec<u8>) {
+        append_bools(self.iter(), buffer);
+    }
+}
+
+/**
+ * Packs the given bools into bytes, in the same way as U8VecBitOutput, and adds them to the buffer. The last
+ * byte is padded with false bits.
+ */
+fn append_bools<B: Iterator<Item = bool>>(bools: B, buffer: &mut Vec<u8>) {
+    let mut byte = [false; 8];
+    let mut bool_index = 0;
+    for value in bools {
+        byte[bool_index] = value;
+        bool_index += 1;
+        if bool_index == 8 {
+            buffer.push(bool_array_to_i8(byte) as u8);
+            byte = [false; 8];
+            bool_index = 0;
+        }
+    }
+    if bool_index > 0 {
+        buffer.push(bool_array_to_i8(byte) as u8);
+    }
+}
+
+/**
+ * Reads the frames that were written by a FrameWriter from a byte stream. Every frame is returned as a
+ * U8VecBitInput that can only read the bits of that frame.
+ *
+ * The header of every frame is read 1 byte at a time, so every header byte would be a separate system call on
+ * an unbuffered reader. Readers like TcpStream and File should therefore be wrapped in a std::io::BufReader.
+ *
+ * The header of a frame is read before its bytes are allocated, so the reader can refuse frames that are
+ * larger than max_frame_bytes. This prevents a malicious peer from letting the application allocate huge
+ * amounts of memory by sending a big header.
+ */
+pub struct FrameReader<R: Read> {
+    reader: R,
+    max_frame_bytes: usize,
+}
+
+impl<R: Read> FrameReader<R> {
+    /**
+     * Creates a new FrameReader that reads its frames from the given reader and refuses frames that have more
+     * than max_frame_bytes bytes. Unbuffered readers should be wrapped in a BufReader first (see FrameReader).
+     */
+    pub fn new(reader: R, max_frame_bytes: usize) -> FrameReader<R> {
+        FrameReader {
+            reader,
+            max_frame_bytes,
+        }
+    }
+
+    /**
+     * Reads the next frame and returns a bit input for its message. If the stream ends before the first byte
+     * of the header, Ok(None) will be returned. If the stream ends in the middle of a frame, an Io error will
+     * be returned. If the frame is larger than max_frame_bytes, a FrameTooLarge error will be returned without
+     * reading the bytes of the frame.
+     */
+    pub fn read_frame(&mut self) -> Result<Option<U8VecBitInput>, FrameError> {
+        let bit_len = match self.read_frame_header()? {
+            Some(bit_len) => bit_len,
+            None => return Ok(None),
+        };
+        let byte_len = bit_len.div_ceil(8);
+        if byte_len > self.max_frame_bytes as u64 {
+            return Err(FrameError::FrameTooLarge {
+                frame_bytes: byte_len,
+                max_frame_bytes: self.max_frame_bytes,
+            });
+        }
+        let mut bytes = vec![0; byte_len as usize];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(Some(U8VecBitInput::with_bit_len(bytes, bit_len as usize)))
+    }
+
+    fn read_frame_header(&mut self) -> Result<Option<u64>, FrameError> {
+        let mut bit_len = 0;
+        for byte_index in 0..MAX_HEADER_BYTES {
+            let mut byte = [0];
+            if let Err(error) = self.reader.read_exact(&mut byte) {
+                if byte_index == 0 && error.kind() == std::io::ErrorKind::UnexpectedEof {
+                    return Ok(None);
+                }
+                return Err(FrameError::Io(error));
+            }
+            let value = (byte[0] & 0x7F) as u64;
+            if byte_index == MAX_HEADER_BYTES - 1 && value > 1 {
+                return Err(FrameError::InvalidHeader);
+            }
+            bit_len |= value << (7 * byte_index);
+            if byte[0] & 0x80 == 0 {
+                return Ok(Some(bit_len));
+            }
+        }
+        Err(FrameError::InvalidHeader)
+    }
+
+    /**
+     * Returns a mutable reference to the underlying reader.
+     */
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /**
+     * Returns the underlying reader.
+     */
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/**
+ * Stores the given value as unsigned LEB128 in the given buffer and returns the number of used bytes.
+ */
+fn encode_frame_header(mut value: u64, buffer: &mut [u8; MAX_HEADER_BYTES]) -> usize {
+    let mut length = 0;
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            buffer[length] = byte;
+            return length + 1;
+        }
+        buffer[length] = byte | 0x80;
+        length += 1;
+    }
+}
+
+/**
+ * The errors that can occur while writing or reading frames. The Io variant wraps the errors of the
+ * underlying stream, including the UnexpectedEof error when the stream ends in the middle of a frame.
+ */
+#[derive(Debug)]
+pub enum FrameError {
+    Io(std::io::Error),
+    FrameTooLarge {
+        frame_bytes: u64,
+        max_frame_bytes: usize,
+    },
+    InvalidHeader,
+}
+
+impl std::convert::From<std::io::Error> for FrameError {
+    fn from(error: std::io::Error) -> FrameError {
+        FrameError::Io(error)
+    }
+}
+
+impl std::fmt::Display for FrameError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FrameError::Io(error) => write!(formatter, "Frame IO error: {}", error),
+            FrameError::FrameTooLarge {
+                frame_bytes,
+                max_frame_bytes,
+            } => write!(
+                formatter,
+                "The frame has {} bytes, but at most {} bytes are allowed",
+                frame_bytes, max_frame_bytes
+            ),
+            FrameError::InvalidHeader => write!(formatter, "The frame header is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FrameError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use crate::input::*;
+use crate::output::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::OnceLock;
+
+/**
+ * The number of symbols of a HuffmanTable. Every byte value is a symbol.
+ */
+pub const HUFFMAN_SYMBOLS: usize = 256;
+
+/**
+ * The maximum length of a single Huffman code. The code lengths are stored with 5 bits each when a
+ * table is added to a BitOutput, so this can't be larger than 31.
+ */
+pub const MAX_HUFFMAN_CODE_LENGTH: usize = 24;
+
+/**
+ * A canonical Huffman code table for bytes. Such a table can be used to store strings and byte arrays
+ * compactly with the add_huffman_string and add_huffman_bytes methods of BitOutput: bytes that occur
+ * often get short codes and bytes that occur rarely get long codes.
+ *
+ * The default table for English text can be obtained with HuffmanTable::english(). Applications that
+ * store other kinds of data can train their own table with HuffmanTable::train or create one from
+ * their own byte frequencies. Such a table can be stored in a BitOutput with add_huffman_table so that
+ * the reader doesn't need to know it in advance.
+ *
+ * Only the code lengths determine the table, so the table that is used for reading will always be
+ * exactly the same as the table that was used for writing.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct HuffmanTable {
+    lengths: [u8; HUFFMAN_SYMBOLS],
+    codes: [u32; HUFFMAN_SYMBOLS],
+    // The number of codes of each length, indexed by the code length
+    counts: [u32; MAX_HUFFMAN_CODE_LENGTH + 1],
+    // The first (smallest) code of each length, indexed by the code length
+    first_codes: [u32; MAX_HUFFMAN_CODE_LENGTH + 1],
+    // The index in sorted_symbols of the symbol with the first code of each length
+    first_indices: [u32; MAX_HUFFMAN_CODE_LENGTH + 1],
+    // All symbols that have a code, sorted by code
+    sorted_symbols: Vec<u8>,
+    max_length: usize,
+}
+
+impl HuffmanTable {
+    /**
+     * Creates the optimal Huffman table for data with the given byte frequencies. Bytes with a frequency of 0
+     * will not get a code and can thus not be stored with the resulting table.
+     *
+     * If the optimal code would need codes that are longer than MAX_HUFFMAN_CODE_LENGTH, the frequencies will
+     * be flattened until all codes are short enough.
+     */
+    pub fn from_frequencies(frequencies: &[u64; HUFFMAN_SYMBOLS]) -> HuffmanTable {
+        let mut frequencies = *frequencies;
+        loop {
+            let lengths = compute_code_lengths(&frequencies);
+            if lengths
+                .iter()
+                .all(|length| *length as usize <= MAX_HUFFMAN_CODE_LENGTH)
+            {
+                return HuffmanTable::from_lengths(lengths)
+                    .expect("Huffman code lengths should be valid");
+            }
+            for frequency in frequencies.iter_mut() {
+                if *frequency > 0 {
+                    *frequency = (*frequency >> 1) | 1;
+                }
+            }
+        }
+    }
+
+    /**
+     * Creates a Huffman table that is optimized for the given samples. Every byte value will get a code, even
+     * if it doesn't occur in the samples, so the resulting table can store any string or byte array.
+     */
+    pub fn train<S: AsRef<[u8]>>(samples: &[S]) -> HuffmanTable {
+        let mut frequencies = [1; HUFFMAN_SYMBOLS];
+        for sample in samples {
+            for byte in sample.as_ref() {
+                frequencies[*byte as usize] += 1;
+            }
+        }
+        HuffmanTable::from_frequencies(&frequencies)
+    }
+
+    /**
+     * Returns the default Huffman table for English (ASCII) text. Lowercase letters and spaces get the shortest
+     * codes. Every byte value has a code, so any string can be stored with this table, but non-ASCII characters
+     * will take more bits than usual.
+     *
+     * The table is only built the first time this function is called. Later calls return the same table.
+     */
+    pub fn english() -> &'static HuffmanTable {
+        static ENGLISH: OnceLock<HuffmanTable> = OnceLock::new();
+        ENGLISH.get_or_init(HuffmanTable::create_english)
+    }
+
+    fn create_english() -> HuffmanTable {
+        let mut frequencies = [1; HUFFMAN_SYMBOLS];
+        for byte in b' '..=b'~' {
+            frequencies[byte as usize] = 3;
+        }
+        for (byte, frequency) in ENGLISH_FREQUENCIES.iter() {
+            frequencies[*byte as usize] = *frequency;
+            if byte.is_ascii_lowercase() {
+                frequencies[byte.to_ascii_uppercase() as usize] = (*frequency / 10).max(2);
+            }
+        }
+        for digit in b'0'..=b'9' {
+            frequencies[digit as usize] = 20;
+        }
+        HuffmanTable::from_frequencies(&frequencies)
+    }
+
+    /**
+     * Creates a Huffman table from the code length of every byte value. A length of 0 means that the byte will
+     * not get a code. Returns None if the lengths don't form a valid prefix code.
+     */
+    pub fn from_lengths(lengths: [u8; HUFFMAN_SYMBOLS]) -> Option<HuffmanTable> {
+        let mut counts = [0u32; MAX_HUFFMAN_CODE_LENGTH + 1];
+        for length in lengths.iter() {
+            if *length as usize > MAX_HUFFMAN_CODE_LENGTH {
+                return None;
+            }
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Check that the code is not over-subscribed (the Kraft inequality)
+        let mut kraft_sum = 0u64;
+        for (length, count) in counts.iter().enumerate().skip(1) {
+            kraft_sum += (*count as u64) << (MAX_HUFFMAN_CODE_LENGTH - length);
+        }
+        if kraft_sum > 1 << MAX_HUFFMAN_CODE_LENGTH {
+            return None;
+        }
+
+        let mut first_codes = [0u32; MAX_HUFFMAN_CODE_LENGTH + 1];
+        let mut first_indices = [0u32; MAX_HUFFMAN_CODE_LENGTH + 1];
+        let mut code = 0;
+        let mut index = 0;
+        for length in 1..=MAX_HUFFMAN_CODE_LENGTH {
+            code = (code + counts[length - 1]) << 1;
+            first_codes[length] = code;
+            first_indices[length] = index;
+            index += counts[length];
+        }
+
+        let mut next_codes = first_codes;
+        let mut codes = [0u32; HUFFMAN_SYMBOLS];
+        let mut sorted_symbols = vec![0u8; index as usize];
+        for (symbol, length) in lengths.iter().enumerate() {
+            let length = *length as usize;
+            if length > 0 {
+                codes[symbol] = next_codes[length];
+                let sorted_index = first_indices[length] + next_codes[length] - first_codes[length];
+                sorted_symbols[sorted_index as usize] = symbol as u8;
+                next_codes[length] += 1;
+            }
+        }
+
+        Some(HuffmanTable {
+            lengths,
+            codes,
+            counts,
+            first_codes,
+            first_indices,
+            sorted_symbols,
+            max_length: *lengths.iter().max().unwrap() as usize,
+        })
+    }
+
+    /**
+     * Returns the code length of every byte value. A length of 0 means that the byte doesn't have a code.
+     */
+    pub fn lengths(&self) -> &[u8; HUFFMAN_SYMBOLS] {
+        &self.lengths
+    }
+
+    /**
+     * Returns the number of bits that would be needed to store the given bytes with this table, without the
+     * length prefix. Returns None if one of the bytes doesn't have a code.
+     */
+    pub fn encoded_bits(&self, bytes: &[u8]) -> Option<usize> {
+        let mut bits = 0;
+        for byte in bytes {
+            match self.lengths[*byte as usize] {
+                0 => return None,
+                length => bits += length as usize,
+            }
+        }
+        Some(bits)
+    }
+
+    /**
+     * Adds the code of the given byte to the output without checking its capacity. Panics if the byte doesn't
+     * have a code.
+     */
+    pub(crate) fn add_direct_symbol<O: BitOutput + ?Sized>(&self, output: &mut O, symbol: u8) {
+        let length = self.lengths[symbol as usize] as usize;
+        if length == 0 {
+            panic!(
+                "The byte {} doesn't have a code in this Huffman table",
+                symbol
+            );
+        }
+        output.add_direct_sized_u64(self.codes[symbol as usize] as u64, length);
+    }
+
+    /**
+     * Reads the next code from the input and returns the corresponding byte.
+     */
+    pub(crate) fn read_symbol<I: BitInput + ?Sized>(
+        &self,
+        input: &mut I,
+    ) -> Result<u8, BitInputError> {
+        let mut code = 0;
+        for length in 1..=self.max_length {
+            code = (code << 1) | input.read_bool()? as u32;
+            let offset = code.wrapping_sub(self.first_codes[length]);
+            if offset < self.counts[length] {
+                let index = self.first_indices[length] + offset;
+                return Ok(self.sorted_symbols[index as usize]);
+            }
+        }
+        Err(BitInputError::InvalidCode(InvalidCodeError))
+    }
+}
+
+// The approximate number of occurrences per 10000 characters of English text
+const ENGLISH_FREQUENCIES: [(u8, u64); 45] = [
+    (b' ', 1800),
+    (b'e', 1000),
+    (b't', 740),
+    (b'a', 650),
+    (b'o', 600),
+    (b'i', 570),
+    (b'n', 560),
+    (b's', 510),
+    (b'h', 490),
+    (b'r', 480),
+    (b'd', 340),
+    (b'l', 320),
+    (b'c', 220),
+    (b'u', 220),
+    (b'm', 200),
+    (b'w', 190),
+    (b'f', 180),
+    (b'g', 160),
+    (b'y', 160),
+    (b'p', 150),
+    (b'b', 120),
+    (b'v', 80),
+    (b'k', 60),
+    (b'j', 10),
+    (b'x', 10),
+    (b'q', 8),
+    (b'z', 6),
+    (b'.', 100),
+    (b',', 100),
+    (b'\n', 80),
+    (b'\'', 25),
+    (b'"', 20),
+    (b'-', 20),
+    (b'!', 8),
+    (b'?', 8),
+    (b':', 8),
+    (b';', 5),
+    (b'(', 5),
+    (b')', 5),
+    (b'/', 4),
+    (b'&', 3),
+    (b'*', 3),
+    (b'_', 3),
+    (b'\t', 3),
+    (b'\r', 3),
+];
+
+/**
+ * Computes the optimal code length of every symbol with the given frequencies. Symbols with a frequency of 0
+ * get a length of 0. If only 1 symbol has a non-zero frequency, it gets a length of 1.
+ */
+fn compute_code_lengths(frequencies: &[u64; HUFFMAN_SYMBOLS]) -> [u8; HUFFMAN_SYMBOLS] {
+    // The first HUFFMAN_SYMBOLS nodes are the leaves and the other nodes are created while merging
+    let mut parents = vec![usize::MAX; 2 * HUFFMAN_SYMBOLS];
+    let mut heap = BinaryHeap::new();
+    for (symbol, frequency) in frequencies.iter().enumerate() {
+        if *frequency > 0 {
+            heap.push(Reverse((*frequency, symbol)));
+        }
+    }
+
+    let mut lengths = [0u8; HUFFMAN_SYMBOLS];
+    if heap.len() == 1 {
+        let Reverse((_, symbol)) = heap.pop().unwrap();
+        lengths[symbol] = 1;
+        return lengths;
+    }
+
+    let mut next_node = HUFFMAN_SYMBOLS;
+    while heap.len() > 1 {
+        let Reverse((frequency1, node1)) = heap.pop().unwrap();
+        let Reverse((frequency2, node2)) = heap.pop().unwrap();
+        parents[node1] = next_node;
+        parents[node2] = next_node;
+        heap.push(Reverse((frequency1.saturating_add(frequency2), next_node)));
+        next_node += 1;
+    }
+
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        let mut node = symbol;
+        let mut depth = 0usize;
+        while parents[node] != usize::MAX {
+            node = parents[node];
+            depth += 1;
+        }
+        *length = depth.min(u8::MAX as usize) as u8;
+    }
+    lengths
+}
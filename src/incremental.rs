@@ -0,0 +1,199 @@
+use crate::converter::*;
+use crate::input::*;
+
+/**
+ * A BitInput for data that arrives in pieces, for instance from a non-blocking socket. Byte chunks can be fed
+ * to it whenever they arrive and messages can be parsed with try_parse as soon as they are complete.
+ *
+ * The input remembers a committed mark: the position right after the last message that was parsed
+ * successfully. When a parse reads beyond the data that has been fed so far, try_parse rolls back to the mark
+ * and returns a NeedMoreDataError, so the same parse can simply be tried again after more data has been fed.
+ * The bytes before the mark are discarded from time to time, so the buffer only needs to hold the data of
+ * the messages that have not been parsed yet.
+ *
+ * The bytes are read in the same way as U8VecBitInput reads them, so the data can be written with a
+ * U8VecBitOutput.
+ */
+pub struct IncrementalBitInput {
+    buffer: Vec<u8>,
+    byte_index: usize,
+    bool_index: usize,
+    mark_byte_index: usize,
+    mark_bool_index: usize,
+    finished: bool,
+}
+
+impl IncrementalBitInput {
+    /**
+     * Creates a new IncrementalBitInput that doesn't have any data yet.
+     */
+    pub fn new() -> IncrementalBitInput {
+        IncrementalBitInput::with_capacity(0)
+    }
+
+    /**
+     * Creates a new IncrementalBitInput that doesn't have any data yet, but can hold the given number of bytes
+     * without reallocating.
+     */
+    pub fn with_capacity(capacity: usize) -> IncrementalBitInput {
+        IncrementalBitInput {
+            buffer: Vec::with_capacity(capacity),
+            byte_index: 0,
+            bool_index: 0,
+            mark_byte_index: 0,
+            mark_bool_index: 0,
+            finished: false,
+        }
+    }
+
+    /**
+     * Adds the given bytes to the end of the data of this input. This function will panic if finish has been
+     * called already.
+     */
+    pub fn feed(&mut self, bytes: &[u8]) {
+        if self.finished {
+            panic!("Can't feed data after finish has been called");
+        }
+        // Only discard the committed bytes when that is relatively cheap, to avoid moving the same bytes often
+        if self.mark_byte_index > 0 && 2 * self.mark_byte_index >= self.buffer.len() {
+            self.buffer.drain(0..self.mark_byte_index);
+            self.byte_index -= self.mark_byte_index;
+            self.mark_byte_index = 0;
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /**
+     * Tells this input that no more data will be fed, for instance because the connection has been closed.
+     * After this call, try_parse will return an InputCapacityError instead of a NeedMoreDataError when a
+     * message is incomplete, because it will never be completed.
+     */
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /**
+     * Returns true if finish has been called.
+     */
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /**
+     * Returns the number of bits that have been fed, but not read yet.
+     */
+    pub fn available_bits(&self) -> usize {
+        8 * (self.buffer.len() - self.byte_index) - self.bool_index
+    }
+
+    /**
+     * Commits the current position: all data before it has been parsed and rollback will return to this
+     * position from now on.
+     */
+    pub fn mark(&mut self) {
+        self.mark_byte_index = self.byte_index;
+        self.mark_bool_index = self.bool_index;
+    }
+
+    /**
+     * Returns to the position of the last mark, so the data after it can be parsed again.
+     */
+    pub fn rollback(&mut self) {
+        self.byte_index = self.mark_byte_index;
+        self.bool_index = self.mark_bool_index;
+    }
+
+    /**
+     * Parses the next message with the given parse function. If the parse succeeds, the position after the
+     * message is marked and the result is returned. If the parse fails, this input is rolled back to the last
+     * mark. If it failed because the data that has been fed so far is incomplete (and finish hasn't been
+     * called), a NeedMoreDataError will be returned and the parse should be tried again after more data has
+     * been fed. Any other error is returned as-is, including the InputCapacityErrors of other inputs that the
+     * parse function reads from (for instance bounded inputs that it creates), since more data can't fix those.
+     */
+    pub fn try_parse<T, F>(&mut self, parse: F) -> Result<T, BitInputError>
+    where
+        F: FnOnce(&mut IncrementalBitInput) -> Result<T, BitInputError>,
+    {
+        let max_capacity = 8 * self.buffer.len();
+        match parse(self) {
+            Ok(result) => {
+                self.mark();
+                Ok(result)
+            }
+            // Only the capacity errors of this input itself can be solved by feeding more data
+            Err(BitInputError::InputCapacity(error))
+                if !self.finished && error.max_capacity() == max_capacity =>
+            {
+                self.rollback();
+                let missing_bits = (error.current_capacity() + error.requested_extra_capacity())
+                    .saturating_sub(error.max_capacity());
+                Err(BitInputError::NeedMoreData(NeedMoreDataError::new(
+                    missing_bits,
+                )))
+            }
+            Err(error) => {
+                self.rollback();
+                Err(error)
+            }
+        }
+    }
+
+    fn read_index(&self) -> usize {
+        8 * self.byte_index + self.bool_index
+    }
+}
+
+impl Default for IncrementalBitInput {
+    fn default() -> IncrementalBitInput {
+        IncrementalBitInput::new()
+    }
+}
+
+impl BitInput for IncrementalBitInput {
+    fn read_direct_bool(&mut self) -> bool {
+        let result = i8_to_bool_array(self.buffer[self.byte_index] as i8)[self.bool_index];
+        self.bool_index += 1;
+        if self.bool_index == 8 {
+            self.bool_index = 0;
+            self.byte_index += 1;
+        }
+        result
+    }
+
+    fn read_direct_i8(&mut self) -> i8 {
+        if self.bool_index == 0 {
+            let result = self.buffer[self.byte_index] as i8;
+            self.byte_index += 1;
+            result
+        } else {
+            let mut bools = [false; 8];
+            for value in bools.iter_mut() {
+                *value = self.read_direct_bool();
+            }
+            bool_array_to_i8(bools)
+        }
+    }
+
+    fn ensure_extra_capacity(&mut self, extra_bools: usize) -> Result<(), InputCapacityError> {
+        if self.available_bits() < extra_bools {
+            Err(InputCapacityError::new(
+                self.read_index(),
+                8 * self.buffer.len(),
+                extra_bools,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn terminate(&mut self) {
+        self.buffer.clear();
+        self.buffer.shrink_to_fit();
+        self.byte_index = 0;
+        self.bool_index = 0;
+        self.mark_byte_index = 0;
+        self.mark_bool_index = 0;
+        self.finished = true;
+    }
+}
@@ -1,4 +1,8 @@
+use crate::alphabet::*;
 use crate::converter::*;
+use crate::huffman::*;
+use crate::packed::*;
+use crate::rank_select::*;
 
 /**
  * Instances of BitInput can be used to load data that has been stored previously. This trait contains a lot of
@@ -1259,6 +1263,296 @@ pub trait BitInput {
         self.read_sized_u64(bits as usize)
     }
 
+    /**
+     * Reads a positive integer that was stored with the Elias gamma code. If the read bits don't form a valid
+     * code for a u64 (more than 63 leading zeros), an InvalidCodeError will be returned.
+     *
+     * The mirror function of this function is add_elias_gamma.
+     */
+    fn read_elias_gamma(&mut self) -> Result<u64, BitInputError> {
+        let bits = read_unary(self, 63)? as usize;
+        let low_bits = self.read_sized_u64(bits)?;
+        Ok((1 << bits) | low_bits)
+    }
+
+    /**
+     * Reads a positive integer that was stored with the Elias delta code. If the read bits don't form a valid
+     * code for a u64, an InvalidCodeError will be returned.
+     *
+     * The mirror function of this function is add_elias_delta.
+     */
+    fn read_elias_delta(&mut self) -> Result<u64, BitInputError> {
+        let bits = self.read_elias_gamma()? - 1;
+        if bits > 63 {
+            return Err(BitInputError::InvalidCode(InvalidCodeError));
+        }
+        let low_bits = self.read_sized_u64(bits as usize)?;
+        Ok((1 << bits) | low_bits)
+    }
+
+    /**
+     * Reads an integer that was stored with the exponential Golomb code of order k. The order k must be the
+     * same as the order that was used to store the integer. If the read bits don't form a valid code for a
+     * u64, an InvalidCodeError will be returned.
+     *
+     * The mirror function of this function is add_exp_golomb.
+     */
+    fn read_exp_golomb(&mut self, k: usize) -> Result<u64, BitInputError> {
+        if k > 64 {
+            panic!(
+                "The order of the exponential Golomb code must be at most 64, but it is {}",
+                k
+            );
+        }
+        let bits = k + read_unary(self, 64 - k as u64)? as usize;
+        let low_bits = self.read_sized_u64(bits)?;
+        let value = ((1u128 << bits) | low_bits as u128) - (1u128 << k);
+        if value > u64::MAX as u128 {
+            return Err(BitInputError::InvalidCode(InvalidCodeError));
+        }
+        Ok(value as u64)
+    }
+
+    /**
+     * Reads an integer that was stored with the Golomb-Rice code with parameter k. The parameter k must be the
+     * same as the parameter that was used to store the integer. If the read quotient is too large for a u64 or
+     * larger than MAX_RICE_QUOTIENT, an InvalidCodeError will be returned.
+     *
+     * The mirror function of this function is add_rice.
+     */
+    fn read_rice(&mut self, k: usize) -> Result<u64, BitInputError> {
+        if k > 64 {
+            panic!(
+                "The parameter of the Rice code must be at most 64, but it is {}",
+                k
+            );
+        }
+        let max_quotient = if k == 64 {
+            0
+        } else {
+            (u64::MAX >> k).min(crate::output::MAX_RICE_QUOTIENT)
+        };
+        let quotient = read_unary(self, max_quotient)?;
+        let remainder = self.read_sized_u64(k)?;
+        if k == 64 {
+            Ok(remainder)
+        } else {
+            Ok((quotient << k) | remainder)
+        }
+    }
+
+    /**
+     * Reads a sorted sequence of u64s that was stored with add_sorted_u64_seq. The gap codec doesn't need to
+     * be given because it is stored in the input.
+     *
+     * The max_length is the maximum number of values that may be read. If the stored length is larger than
+     * max_length, a LengthError will be returned instead of allocating memory for the values. If the sum
+     * of the gaps doesn't fit in a u64, an IntegerOverflowError will be returned.
+     *
+     * The mirror function of this function is add_sorted_u64_seq.
+     */
+    fn read_sorted_u64_seq(&mut self, max_length: usize) -> Result<Vec<u64>, BitInputError> {
+        let length = read_length(self, max_length)?;
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+        let mut value = self.read_var_u64()?;
+        // Every gap takes at least 1 bit, so this prevents allocating memory for corrupted lengths
+        self.ensure_extra_capacity(length - 1)?;
+        let mut values = Vec::with_capacity(length);
+        values.push(value);
+        if length > 1 {
+            let rice_parameter = if self.read_bool()? {
+                Some(self.read_sized_u64(6)? as usize)
+            } else {
+                None
+            };
+            for _ in 1..length {
+                let gap = match rice_parameter {
+                    Some(k) => self.read_rice(k)?,
+                    None => self.read_var_u64()?,
+                };
+                value = value
+                    .checked_add(gap)
+                    .ok_or(BitInputError::IntegerOverflow(IntegerOverflowError))?;
+                values.push(value);
+            }
+        }
+        Ok(values)
+    }
+
+    /**
+     * Reads i64s that were stored with add_packed_i64_slice.
+     *
+     * The max_length is the maximum number of values that may be read. If the stored length is larger than
+     * max_length, a LengthError will be returned instead of allocating memory for the values. If the
+     * stored bit width is invalid, an InvalidCodeError will be returned and if a value doesn't fit in an i64,
+     * an IntegerOverflowError will be returned.
+     *
+     * The mirror function of this function is add_packed_i64_slice.
+     */
+    fn read_packed_i64_vec(&mut self, max_length: usize) -> Result<Vec<i64>, BitInputError> {
+        read_packed_i64s(self, max_length, false)
+    }
+
+    /**
+     * Reads u32s that were stored with add_packed_u32_slice. The max_length and the errors work the same way as
+     * in read_packed_i64_vec.
+     *
+     * The mirror function of this function is add_packed_u32_slice.
+     */
+    fn read_packed_u32_vec(&mut self, max_length: usize) -> Result<Vec<u32>, BitInputError> {
+        read_packed_u32s(self, max_length, false)
+    }
+
+    /**
+     * Reads i64s that were stored with add_patched_i64_slice. The max_length and the errors work the same way
+     * as in read_packed_i64_vec. An InvalidCodeError will also be returned if the stored exceptions are invalid.
+     *
+     * The mirror function of this function is add_patched_i64_slice.
+     */
+    fn read_patched_i64_vec(&mut self, max_length: usize) -> Result<Vec<i64>, BitInputError> {
+        read_packed_i64s(self, max_length, true)
+    }
+
+    /**
+     * Reads u32s that were stored with add_patched_u32_slice. The max_length and the errors work the same way
+     * as in read_patched_i64_vec.
+     *
+     * The mirror function of this function is add_patched_u32_slice.
+     */
+    fn read_patched_u32_vec(&mut self, max_length: usize) -> Result<Vec<u32>, BitInputError> {
+        read_packed_u32s(self, max_length, true)
+    }
+
+    /**
+     * Reads bools that were stored with add_rle_bools.
+     *
+     * The max_length is the maximum number of bools that may be read. If the stored length is larger than
+     * max_length, a LengthError will be returned instead of allocating memory for the bools. If the sum
+     * of the run lengths is not equal to the stored length, an InvalidCodeError will be returned.
+     *
+     * The mirror function of this function is add_rle_bools.
+     */
+    fn read_rle_bools(&mut self, max_length: usize) -> Result<Vec<bool>, BitInputError> {
+        let length = read_length(self, max_length)?;
+        let mut bools = Vec::with_capacity(length);
+        if length > 0 {
+            let mut value = self.read_bool()?;
+            while bools.len() < length {
+                let run_length = read_run_length(self, length - bools.len())?;
+                bools.resize(bools.len() + run_length, value);
+                value = !value;
+            }
+        }
+        Ok(bools)
+    }
+
+    /**
+     * Reads u32s that were stored with add_rle_u32_slice. The max_length and the errors work the same way as in
+     * read_rle_bools. If a stored value is larger than u32::MAX, an IntegerOverflowError will be returned.
+     *
+     * The mirror function of this function is add_rle_u32_slice.
+     */
+    fn read_rle_u32_vec(&mut self, max_length: usize) -> Result<Vec<u32>, BitInputError> {
+        let length = read_length(self, max_length)?;
+        let mut values = Vec::with_capacity(length);
+        while values.len() < length {
+            let value = self.read_var_u64()?;
+            if value > u32::MAX as u64 {
+                return Err(BitInputError::IntegerOverflow(IntegerOverflowError));
+            }
+            let run_length = read_run_length(self, length - values.len())?;
+            values.resize(values.len() + run_length, value as u32);
+        }
+        Ok(values)
+    }
+
+    /**
+     * Reads bools that were stored with add_auto_rle_bools. The max_length and the errors work the same way as
+     * in read_rle_bools.
+     *
+     * The mirror function of this function is add_auto_rle_bools.
+     */
+    fn read_auto_rle_bools(&mut self, max_length: usize) -> Result<Vec<bool>, BitInputError> {
+        if self.read_bool()? {
+            self.read_rle_bools(max_length)
+        } else {
+            let length = read_length(self, max_length)?;
+            self.read_bools(length)
+        }
+    }
+
+    /**
+     * Reads u32s that were stored with add_auto_rle_u32_slice. The max_length and the errors work the same way
+     * as in read_rle_u32_vec.
+     *
+     * The mirror function of this function is add_auto_rle_u32_slice.
+     */
+    fn read_auto_rle_u32_vec(&mut self, max_length: usize) -> Result<Vec<u32>, BitInputError> {
+        if self.read_bool()? {
+            self.read_rle_u32_vec(max_length)
+        } else {
+            let length = read_length(self, max_length)?;
+            self.read_u32s(length)
+        }
+    }
+
+    /**
+     * Reads bools that were stored with add_sparse_bools.
+     *
+     * The max_length is the maximum number of bools that may be read. If the stored length is larger than
+     * max_length, a LengthError will be returned instead of allocating memory for the bools. If a stored
+     * index is not smaller than the length or occurs more than once, an InvalidCodeError will be returned.
+     *
+     * The mirror function of this function is add_sparse_bools.
+     */
+    fn read_sparse_bools(&mut self, max_length: usize) -> Result<Vec<bool>, BitInputError> {
+        let length = read_length(self, max_length)?;
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+        let polarity = self.read_bool()?;
+        let indices = self.read_sorted_u64_seq(length)?;
+        let mut bools = vec![!polarity; length];
+        for index in indices {
+            if index >= length as u64 || bools[index as usize] == polarity {
+                return Err(BitInputError::InvalidCode(InvalidCodeError));
+            }
+            bools[index as usize] = polarity;
+        }
+        Ok(bools)
+    }
+
+    /**
+     * Reads a PackedIntVec that was stored with add_packed_int_vec. The integers are copied word by word instead
+     * of one by one.
+     *
+     * The max_length is the maximum number of integers the vector may have. If the stored length is larger than
+     * max_length, a LengthError will be returned instead of allocating memory for the vector. If the stored
+     * bit width is larger than 64, an InvalidCodeError will be returned.
+     *
+     * The mirror function of this function is add_packed_int_vec.
+     */
+    fn read_packed_int_vec(&mut self, max_length: usize) -> Result<PackedIntVec, BitInputError> {
+        PackedIntVec::read_from(self, max_length)
+    }
+
+    /**
+     * Reads a RankSelectBitVec that was stored with add_rank_select_bit_vec and rebuilds its auxiliary index.
+     *
+     * The max_length is the maximum number of bits the vector may have. If the stored length is larger than
+     * max_length, a LengthError will be returned instead of allocating memory for the vector.
+     *
+     * The mirror function of this function is add_rank_select_bit_vec.
+     */
+    fn read_rank_select_bit_vec(
+        &mut self,
+        max_length: usize,
+    ) -> Result<RankSelectBitVec, BitInputError> {
+        RankSelectBitVec::read_from(self, max_length)
+    }
+
     /**
      * Reads an optional string from this bit input. This method uses a weird encoding and returns an option instead
      * of just a string to make it compatible with the java and javascript bithelper variants.
@@ -1277,12 +1571,13 @@ pub trait BitInput {
      * The max_length parameter is only used as a safety check. The length of the string was previously stored
      * in the add_string method of the corresponding bit output. This method will read the length and return
      * an error if the read length is larger than the max_length. The max_length makes sure that corrupted
-     * input will not lead to excessive memory allocation.
+     * input will not lead to excessive memory allocation. Just like in java, the length is measured in UTF-16
+     * code units, so characters outside the basic multilingual plane count as 2.
      *
      * The mirror function of this function is add_string.
      */
     fn read_string(&mut self, max_length: usize) -> Result<Option<String>, BitInputError> {
-        let amount1 = self.read_i8()? as u8;
+        let amount1 = self.read_u8()?;
         if amount1 == 0 {
             return Ok(None);
         }
@@ -1296,10 +1591,7 @@ pub trait BitInput {
                     length32,
                 )));
             }
-            length = self.read_i32()? as usize;
-        }
-        if length == 0 {
-            return Ok(Some(String::from("")));
+            length = length32 as usize;
         }
         if length > max_length {
             return Err(BitInputError::StringLength(StringLengthError::long(
@@ -1307,30 +1599,319 @@ pub trait BitInput {
                 max_length,
             )));
         }
+        if length == 0 {
+            return Ok(Some(String::new()));
+        }
+
         self.ensure_extra_capacity(21)?;
         let min = self.read_direct_u16();
         let bit_count = self.read_direct_sized_u64(5) as usize;
+        if bit_count > 16 {
+            return Err(BitInputError::InvalidString(InvalidStringError));
+        }
+
+        let mut units = Vec::with_capacity(length);
         if bit_count == 0 {
-            let result = String::from_utf16(vec![min; length].as_slice());
-            if result.is_ok() {
-                return Ok(Some(result.unwrap()));
-            } else {
-                return Err(BitInputError::InvalidString(InvalidStringError));
-            }
+            units.resize(length, min);
         } else {
             self.ensure_extra_capacity(bit_count * length)?;
-            let mut chars = vec![0; length];
-            for index in 0..length {
-                chars[index] = min + self.read_direct_sized_u64(bit_count) as u16;
+            for _ in 0..length {
+                let offset = self.read_direct_sized_u64(bit_count) as u16;
+                match min.checked_add(offset) {
+                    Some(unit) => units.push(unit),
+                    None => return Err(BitInputError::InvalidString(InvalidStringError)),
+                }
+            }
+        }
+        match String::from_utf16(&units) {
+            Ok(string) => Ok(Some(string)),
+            Err(_) => Err(BitInputError::InvalidString(InvalidStringError)),
+        }
+    }
+
+    /**
+     * Reads a string that was stored with the native Rust encoding from this bit input.
+     *
+     * The max_length is the maximum number of UTF-8 bytes the string may have. If the stored length is larger
+     * than max_length, a StringLengthError will be returned instead of allocating memory for the string. If
+     * the stored bytes are not valid UTF-8, an InvalidStringError will be returned.
+     *
+     * The mirror function of this function is add_rust_string.
+     */
+    fn read_rust_string(&mut self, max_length: usize) -> Result<String, BitInputError> {
+        let length = read_string_length(self, max_length)?;
+        let bytes = self.read_u8s(length)?;
+        String::from_utf8(bytes).map_err(|_| BitInputError::InvalidString(InvalidStringError))
+    }
+
+    /**
+     * Reads a string that only consists of ASCII characters from this bit input. Every character takes 7 bits.
+     *
+     * The max_length is the maximum number of characters the string may have. If the stored length is larger
+     * than max_length, a StringLengthError will be returned instead of allocating memory for the string.
+     *
+     * The mirror function of this function is add_ascii_string.
+     */
+    fn read_ascii_string(&mut self, max_length: usize) -> Result<String, BitInputError> {
+        let length = read_string_length(self, max_length)?;
+        self.ensure_extra_capacity(7 * length)?;
+        let mut bytes = Vec::with_capacity(length);
+        for _ in 0..length {
+            bytes.push(self.read_direct_sized_u64(7) as u8);
+        }
+        String::from_utf8(bytes).map_err(|_| BitInputError::InvalidString(InvalidStringError))
+    }
+
+    /**
+     * Reads a string that was stored using the given alphabet from this bit input. The alphabet must have
+     * exactly the same characters in the same order as the alphabet that was used to store the string.
+     *
+     * The max_length is the maximum number of characters the string may have. If the stored length is larger
+     * than max_length, a StringLengthError will be returned instead of allocating memory for the string.
+     *
+     * The mirror function of this function is add_alphabet_string.
+     */
+    fn read_alphabet_string(
+        &mut self,
+        alphabet: &Alphabet,
+        max_length: usize,
+    ) -> Result<String, BitInputError> {
+        let length = read_string_length(self, max_length)?;
+        let mut result = String::with_capacity(length);
+        for _ in 0..length {
+            result.push(alphabet.read_char(self)?);
+        }
+        Ok(result)
+    }
+
+    /**
+     * Reads a Huffman table that was stored with add_huffman_table. If the stored code lengths don't form a valid
+     * prefix code, an InvalidCodeError will be returned.
+     *
+     * The mirror function of this function is add_huffman_table.
+     */
+    fn read_huffman_table(&mut self) -> Result<HuffmanTable, BitInputError> {
+        self.ensure_extra_capacity(5 * HUFFMAN_SYMBOLS)?;
+        let mut lengths = [0u8; HUFFMAN_SYMBOLS];
+        for length in lengths.iter_mut() {
+            *length = self.read_direct_sized_u64(5) as u8;
+        }
+        HuffmanTable::from_lengths(lengths).ok_or(BitInputError::InvalidCode(InvalidCodeError))
+    }
+
+    /**
+     * Reads a byte vector that was stored with the given Huffman table. The table must be the same as the table
+     * that was used to store the bytes.
+     *
+     * The max_length is the maximum number of bytes that may be read. If the stored length is larger than
+     * max_length, a LengthError will be returned instead of allocating memory for the bytes.
+     *
+     * The mirror function of this function is add_huffman_bytes.
+     */
+    fn read_huffman_bytes(
+        &mut self,
+        table: &HuffmanTable,
+        max_length: usize,
+    ) -> Result<Vec<u8>, BitInputError> {
+        let length = read_length(self, max_length)?;
+        read_huffman_symbols(self, table, length)
+    }
+
+    /**
+     * Reads a string that was stored with the given Huffman table. The table must be the same as the table that
+     * was used to store the string.
+     *
+     * The max_length is the maximum number of UTF-8 bytes the string may have. If the stored length is larger
+     * than max_length, a StringLengthError will be returned instead of allocating memory for the string.
+     *
+     * The mirror function of this function is add_huffman_string.
+     */
+    fn read_huffman_string(
+        &mut self,
+        table: &HuffmanTable,
+        max_length: usize,
+    ) -> Result<String, BitInputError> {
+        let length = read_string_length(self, max_length)?;
+        let bytes = read_huffman_symbols(self, table, length)?;
+        String::from_utf8(bytes).map_err(|_| BitInputError::InvalidString(InvalidStringError))
+    }
+}
+
+/**
+ * Reads zeros until a one is read and returns the number of zeros that were read. If more than max_amount
+ * zeros are read, an InvalidCodeError will be returned. This is used by the methods that read universal codes.
+ */
+fn read_unary<I: BitInput + ?Sized>(input: &mut I, max_amount: u64) -> Result<u64, BitInputError> {
+    let mut amount = 0;
+    while !input.read_bool()? {
+        if amount == max_amount {
+            return Err(BitInputError::InvalidCode(InvalidCodeError));
+        }
+        amount += 1;
+    }
+    Ok(amount)
+}
+
+/**
+ * Reads the given number of bytes that were stored with the given Huffman table.
+ */
+fn read_huffman_symbols<I: BitInput + ?Sized>(
+    input: &mut I,
+    table: &HuffmanTable,
+    length: usize,
+) -> Result<Vec<u8>, BitInputError> {
+    // Every byte takes at least 1 bit, so this prevents allocating memory for corrupted lengths
+    input.ensure_extra_capacity(length)?;
+    let mut bytes = Vec::with_capacity(length);
+    for _ in 0..length {
+        bytes.push(table.read_symbol(input)?);
+    }
+    Ok(bytes)
+}
+
+/**
+ * Reads i64s that were stored with add_packed_i64_slice or add_patched_i64_slice.
+ */
+fn read_packed_i64s<I: BitInput + ?Sized>(
+    input: &mut I,
+    max_length: usize,
+    patched: bool,
+) -> Result<Vec<i64>, BitInputError> {
+    let length = read_length(input, max_length)?;
+    if length == 0 {
+        return Ok(Vec::new());
+    }
+    let min = input.read_i64()?;
+    let offsets = read_packed_offsets(input, length, 64, patched)?;
+    offsets
+        .into_iter()
+        .map(|offset| {
+            let value = min as i128 + offset as i128;
+            if value > i64::MAX as i128 {
+                Err(BitInputError::IntegerOverflow(IntegerOverflowError))
+            } else {
+                Ok(value as i64)
             }
-            let result = String::from_utf16(chars.as_slice());
-            if result.is_ok() {
-                return Ok(Some(result.unwrap()));
+        })
+        .collect()
+}
+
+/**
+ * Reads u32s that were stored with add_packed_u32_slice or add_patched_u32_slice.
+ */
+fn read_packed_u32s<I: BitInput + ?Sized>(
+    input: &mut I,
+    max_length: usize,
+    patched: bool,
+) -> Result<Vec<u32>, BitInputError> {
+    let length = read_length(input, max_length)?;
+    if length == 0 {
+        return Ok(Vec::new());
+    }
+    let min = input.read_u32()?;
+    let offsets = read_packed_offsets(input, length, 32, patched)?;
+    offsets
+        .into_iter()
+        .map(|offset| {
+            let value = min as u64 + offset;
+            if value > u32::MAX as u64 {
+                Err(BitInputError::IntegerOverflow(IntegerOverflowError))
             } else {
-                return Err(BitInputError::InvalidString(InvalidStringError));
+                Ok(value as u32)
+            }
+        })
+        .collect()
+}
+
+/**
+ * Reads the bit width and the offsets that were stored by the methods that store packed integer slices. The
+ * bit width can be at most type_width, which is the number of bits of the integer type of the values.
+ */
+fn read_packed_offsets<I: BitInput + ?Sized>(
+    input: &mut I,
+    length: usize,
+    type_width: usize,
+    patched: bool,
+) -> Result<Vec<u64>, BitInputError> {
+    let full_width = input.read_sized_u64(7)? as usize;
+    let width = if patched {
+        input.read_sized_u64(7)? as usize
+    } else {
+        full_width
+    };
+    if full_width > type_width || width > full_width {
+        return Err(BitInputError::InvalidCode(InvalidCodeError));
+    }
+
+    input.ensure_extra_capacity(length.saturating_mul(width))?;
+    let mut offsets = Vec::with_capacity(length);
+    for _ in 0..length {
+        offsets.push(input.read_direct_sized_u64(width));
+    }
+
+    if patched {
+        let exceptions = input.read_sorted_u64_seq(length)?;
+        let high_width = full_width - width;
+        for (position, index) in exceptions.iter().enumerate() {
+            let duplicate = position > 0 && exceptions[position - 1] == *index;
+            if *index >= length as u64 || duplicate {
+                return Err(BitInputError::InvalidCode(InvalidCodeError));
+            }
+            let high_bits = input.read_sized_u64(high_width)?;
+            if high_width > 0 {
+                offsets[*index as usize] |= high_bits << width;
             }
         }
     }
+    Ok(offsets)
+}
+
+/**
+ * Reads the length of a run that was stored by the methods that store run-length encoded values. If the run
+ * is longer than the number of remaining values, an InvalidCodeError will be returned.
+ */
+fn read_run_length<I: BitInput + ?Sized>(
+    input: &mut I,
+    remaining: usize,
+) -> Result<usize, BitInputError> {
+    let run_length = input.read_var_u64()?;
+    if run_length >= remaining as u64 {
+        return Err(BitInputError::InvalidCode(InvalidCodeError));
+    }
+    Ok(run_length as usize + 1)
+}
+
+/**
+ * Reads a string length that was stored with add_var_u64 and returns a StringLengthError if it is larger
+ * than max_length. This is used by the methods that read strings with a var_u64 length.
+ */
+pub(crate) fn read_string_length<I: BitInput + ?Sized>(
+    input: &mut I,
+    max_length: usize,
+) -> Result<usize, BitInputError> {
+    let length = input.read_var_u64()?;
+    if length > max_length as u64 {
+        return Err(BitInputError::StringLength(StringLengthError::long(
+            length.min(i32::MAX as u64) as i32,
+            max_length,
+        )));
+    }
+    Ok(length as usize)
+}
+
+/**
+ * Reads a length or count that was stored with add_var_u64 and returns a LengthError if it is larger than
+ * max_length. This is used by the methods that read sequences and sections with a var_u64 length.
+ */
+pub(crate) fn read_length<I: BitInput + ?Sized>(
+    input: &mut I,
+    max_length: usize,
+) -> Result<usize, BitInputError> {
+    let length = input.read_var_u64()?;
+    if length > max_length as u64 {
+        return Err(BitInputError::Length(LengthError::new(length, max_length)));
+    }
+    Ok(length as usize)
 }
 
 /**
@@ -1341,15 +1922,31 @@ pub trait BitInput {
  * If the input data is trusted however, it should be safe to .unwrap() everything that is being
  * read from the BitInput instance.
  *
- * Currently, there are 3 errors that belong to this enum, namely InputCapacityError, InvalidStringError
- * and StringLengthError. The latter 2 are only applicable when reading strings. The first one can be
- * caused by almost any method.
+ * The InputCapacityError can be caused by almost any method. The InvalidStringError and StringLengthError
+ * are only applicable when reading strings. The InvalidCodeError is returned by methods that read data
+ * that was stored with a variable-length code (like a Huffman code) when the read bits are not a valid code.
+ * The IntegerOverflowError is returned when a value that is computed from the read data (like the sum of the
+ * gaps of a sorted sequence) doesn't fit in its integer type. The NeedMoreDataError is only returned by
+ * IncrementalBitInput when a message is not yet complete. The ChecksumMismatchError is returned by
+ * ChecksummedBitInput when the checksum of a section doesn't match its data. The UncorrectableError is
+ * returned by the forward error correction readers when the data has more errors than they can correct. The
+ * InvalidMagicError is returned by ContainerReader when the data doesn't start with the expected magic bytes.
+ * The LengthError is returned when a stored length or count that isn't the length of a string (like the
+ * number of values of a sequence or the number of bits of a section) is larger than the allowed maximum.
  */
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum BitInputError {
     InputCapacity(InputCapacityError),
     InvalidString(InvalidStringError),
     StringLength(StringLengthError),
+    InvalidCode(InvalidCodeError),
+    IntegerOverflow(IntegerOverflowError),
+    NeedMoreData(NeedMoreDataError),
+    ChecksumMismatch(ChecksumMismatchError),
+    Uncorrectable(UncorrectableError),
+    InvalidMagic(InvalidMagicError),
+    Length(LengthError),
 }
 
 impl std::fmt::Display for BitInputError {
@@ -1452,6 +2049,219 @@ impl std::error::Error for InvalidStringError {
     }
 }
 
+/**
+ * Some data is stored using a variable-length code, for instance a Huffman code. If the bits that are read
+ * from the bit input do not form a valid code (or a valid code table), this error will be returned. This can
+ * only happen if the input data is corrupted or if it is read in a different way than it was written.
+ */
+#[derive(Debug, PartialEq)]
+pub struct InvalidCodeError;
+
+impl std::fmt::Display for InvalidCodeError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "Attempted to read an invalid code")
+    }
+}
+
+impl std::error::Error for InvalidCodeError {
+    fn description(&self) -> &str {
+        "Attempted to read an invalid code"
+    }
+}
+
+/**
+ * Some values are not stored directly, but computed from other stored values, for instance the values of a
+ * sorted sequence are the sums of the stored gaps. If such a computed value doesn't fit in its integer type,
+ * this error will be returned. This can only happen if the input data is corrupted or if it is read in a
+ * different way than it was written.
+ */
+#[derive(Debug, PartialEq)]
+pub struct IntegerOverflowError;
+
+impl std::fmt::Display for IntegerOverflowError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "A value that was read from the input overflowed")
+    }
+}
+
+impl std::error::Error for IntegerOverflowError {
+    fn description(&self) -> &str {
+        "A value that was read from the input overflowed"
+    }
+}
+
+/**
+ * This error is returned by the try_parse method of IncrementalBitInput when the data that has been fed so far
+ * ends in the middle of the message that is being parsed. Unlike the InputCapacityError, this doesn't mean
+ * that the data is invalid: the parse should simply be tried again after more data has been fed.
+ */
+#[derive(Debug, PartialEq)]
+pub struct NeedMoreDataError {
+    missing_bits: usize,
+}
+
+impl NeedMoreDataError {
+    pub(crate) fn new(missing_bits: usize) -> NeedMoreDataError {
+        NeedMoreDataError { missing_bits }
+    }
+
+    /**
+     * Returns the number of bits that were missing when the parse failed. The message needs at least this many
+     * extra bits, but it can need more because the parse stopped at the first read that couldn't be completed.
+     */
+    pub fn missing_bits(&self) -> usize {
+        self.missing_bits
+    }
+}
+
+impl std::fmt::Display for NeedMoreDataError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "The message is incomplete: at least {} more bits are needed",
+            self.missing_bits
+        )
+    }
+}
+
+impl std::error::Error for NeedMoreDataError {
+    fn description(&self) -> &str {
+        "The message is incomplete and more data is needed"
+    }
+}
+
+/**
+ * This error is returned when the checksum that was stored with some data is not equal to the checksum that
+ * was computed from the data that was read. This means that the data (or the checksum itself) is corrupted,
+ * so none of it should be trusted.
+ */
+#[derive(Debug, PartialEq)]
+pub struct ChecksumMismatchError {
+    stored_checksum: u32,
+    computed_checksum: u32,
+}
+
+impl ChecksumMismatchError {
+    pub(crate) fn new(stored_checksum: u32, computed_checksum: u32) -> ChecksumMismatchError {
+        ChecksumMismatchError {
+            stored_checksum,
+            computed_checksum,
+        }
+    }
+
+    pub fn stored_checksum(&self) -> u32 {
+        self.stored_checksum
+    }
+
+    pub fn computed_checksum(&self) -> u32 {
+        self.computed_checksum
+    }
+}
+
+impl std::fmt::Display for ChecksumMismatchError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "The stored checksum is {:08x}, but the computed checksum is {:08x}",
+            self.stored_checksum, self.computed_checksum
+        )
+    }
+}
+
+impl std::error::Error for ChecksumMismatchError {
+    fn description(&self) -> &str {
+        "The checksum of the data doesn't match"
+    }
+}
+
+/**
+ * Data that is protected with an error correcting code (like a Hamming code or a Reed-Solomon code) can only be
+ * repaired if it doesn't have too many errors. When the reader detects that there are more errors than it can
+ * correct, this error will be returned instead of returning data that is known to be wrong.
+ */
+#[derive(Debug, PartialEq)]
+pub struct UncorrectableError;
+
+impl std::fmt::Display for UncorrectableError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "The data has too many errors to correct")
+    }
+}
+
+impl std::error::Error for UncorrectableError {
+    fn description(&self) -> &str {
+        "The data has too many errors to correct"
+    }
+}
+
+/**
+ * Containers start with a couple of magic bytes that identify the format of the data. If the data doesn't
+ * start with the expected magic bytes, it was probably not created by this application (or it is not even a
+ * container), so this error will be returned before anything else is read.
+ */
+#[derive(Debug, PartialEq)]
+pub struct InvalidMagicError;
+
+impl std::fmt::Display for InvalidMagicError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "The data doesn't start with the expected magic bytes"
+        )
+    }
+}
+
+impl std::error::Error for InvalidMagicError {
+    fn description(&self) -> &str {
+        "The data doesn't start with the expected magic bytes"
+    }
+}
+
+/**
+ * This error is returned when a stored length or count is larger than the maximum that was given by the
+ * caller. Just like the StringLengthError, it prevents corrupted or malicious data from letting the
+ * application allocate huge amounts of memory. Unlike the StringLengthError, it is used for everything that
+ * isn't a string, like the number of values of a sequence or the number of bits of a section.
+ */
+#[derive(Debug, PartialEq)]
+pub struct LengthError {
+    read_length: u64,
+    max_length: usize,
+}
+
+impl LengthError {
+    pub fn new(read_length: u64, max_length: usize) -> LengthError {
+        LengthError {
+            read_length,
+            max_length,
+        }
+    }
+
+    pub fn read_length(&self) -> u64 {
+        self.read_length
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl std::fmt::Display for LengthError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "Read length {}, but the maximum allowed length is {}",
+            self.read_length, self.max_length
+        )
+    }
+}
+
+impl std::error::Error for LengthError {
+    fn description(&self) -> &str {
+        "The read length is larger than the maximum allowed length"
+    }
+}
+
 /**
  * This is the most common BitInputError. This one will be returned when an attempt is made to read more
  * data from the BitInput than it has. This could happen when for instance not all data has been loaded
@@ -1465,6 +2275,18 @@ pub struct InputCapacityError {
 }
 
 impl InputCapacityError {
+    pub(crate) fn new(
+        current_capacity: usize,
+        max_capacity: usize,
+        requested_extra_capacity: usize,
+    ) -> InputCapacityError {
+        InputCapacityError {
+            current_capacity,
+            max_capacity,
+            requested_extra_capacity,
+        }
+    }
+
     pub fn current_capacity(&self) -> usize {
         self.current_capacity
     }
@@ -1557,6 +2379,8 @@ pub struct I8VecBitInput {
     vector: Vec<i8>,
     byte_index: usize,
     bool_index: usize,
+    // The number of bits of the vector that can be read, the remaining bits are padding
+    bit_len: usize,
 }
 
 impl BitInput for I8VecBitInput {
@@ -1600,11 +2424,13 @@ impl BitInput for I8VecBitInput {
     }
 
     fn ensure_extra_capacity(&mut self, boolean_amount: usize) -> Result<(), InputCapacityError> {
-        let remaining = 8 * (self.vector.len() - self.byte_index) - self.bool_index;
+        let remaining = self
+            .bit_len
+            .saturating_sub(8 * self.byte_index + self.bool_index);
         if remaining < boolean_amount {
             Err(InputCapacityError {
-                current_capacity: self.bool_index,
-                max_capacity: self.vector.len(),
+                current_capacity: self.bool_index + 8 * self.byte_index,
+                max_capacity: self.bit_len,
                 requested_extra_capacity: boolean_amount,
             })
         } else {
@@ -1615,6 +2441,40 @@ impl BitInput for I8VecBitInput {
     fn terminate(&mut self) {
         self.vector.clear();
         self.vector.shrink_to_fit();
+        self.bit_len = 0;
+    }
+
+    fn read_direct_u8s_to_slice(&mut self, dest: &mut [u8], start_index: usize, amount: usize) {
+        let bound_index = start_index + amount;
+        if self.bool_index == 0 {
+            // The bytes can be copied directly when this bit input is byte-aligned
+            dest[start_index..bound_index]
+                .iter_mut()
+                .zip(&self.vector[self.byte_index..self.byte_index + amount])
+                .for_each(|(dest, source)| *dest = *source as u8);
+            self.byte_index += amount;
+        } else {
+            for value in &mut dest[start_index..bound_index] {
+                *value = self.read_direct_u8();
+            }
+        }
+    }
+
+    fn read_direct_u8s(&mut self, amount: usize) -> Vec<u8> {
+        if self.bool_index == 0 {
+            let result = self.vector[self.byte_index..self.byte_index + amount]
+                .iter()
+                .map(|byte| *byte as u8)
+                .collect();
+            self.byte_index += amount;
+            result
+        } else {
+            let mut result = Vec::with_capacity(amount);
+            for _ in 0..amount {
+                result.push(self.read_direct_u8());
+            }
+            result
+        }
     }
 }
 
@@ -1624,6 +2484,7 @@ impl I8VecBitInput {
      */
     pub fn new(vector: Vec<i8>) -> I8VecBitInput {
         I8VecBitInput {
+            bit_len: 8 * vector.len(),
             vector: vector,
             byte_index: 0,
             bool_index: 0,
@@ -1636,11 +2497,34 @@ impl I8VecBitInput {
      */
     pub fn with_start_index(vector: Vec<i8>, start_index: usize) -> I8VecBitInput {
         I8VecBitInput {
+            bit_len: 8 * vector.len(),
             vector: vector,
             byte_index: start_index,
             bool_index: 0,
         }
     }
+
+    /**
+     * Creates a new I8VecBitInput that will read from the given vector, but will not read more than the first
+     * bit_len bits of the vector. Attempts to read the remaining bits (the padding) will result in an
+     * InputCapacityError. The bit length can be obtained from the bit_len method of the bit output that
+     * created the vector. This function will panic if bit_len is larger than the number of bits in the vector.
+     */
+    pub fn with_bit_len(vector: Vec<i8>, bit_len: usize) -> I8VecBitInput {
+        if bit_len > 8 * vector.len() {
+            panic!(
+                "The bit length is {}, but the vector has only {} bits",
+                bit_len,
+                8 * vector.len()
+            );
+        }
+        I8VecBitInput {
+            vector,
+            byte_index: 0,
+            bool_index: 0,
+            bit_len,
+        }
+    }
 }
 
 /**
@@ -1654,6 +2538,8 @@ pub struct U8VecBitInput {
     vector: Vec<u8>,
     byte_index: usize,
     bool_index: usize,
+    // The number of bits of the vector that can be read, the remaining bits are padding
+    bit_len: usize,
 }
 
 impl BitInput for U8VecBitInput {
@@ -1697,11 +2583,13 @@ impl BitInput for U8VecBitInput {
     }
 
     fn ensure_extra_capacity(&mut self, boolean_amount: usize) -> Result<(), InputCapacityError> {
-        let remaining = 8 * (self.vector.len() - self.byte_index) - self.bool_index;
+        let remaining = self
+            .bit_len
+            .saturating_sub(8 * self.byte_index + self.bool_index);
         if remaining < boolean_amount {
             Err(InputCapacityError {
                 current_capacity: self.bool_index + 8 * self.byte_index,
-                max_capacity: 8 * self.vector.len(),
+                max_capacity: self.bit_len,
                 requested_extra_capacity: boolean_amount,
             })
         } else {
@@ -1712,6 +2600,35 @@ impl BitInput for U8VecBitInput {
     fn terminate(&mut self) {
         self.vector.clear();
         self.vector.shrink_to_fit();
+        self.bit_len = 0;
+    }
+
+    fn read_direct_u8s_to_slice(&mut self, dest: &mut [u8], start_index: usize, amount: usize) {
+        let bound_index = start_index + amount;
+        if self.bool_index == 0 {
+            // The bytes can be copied directly when this bit input is byte-aligned
+            dest[start_index..bound_index]
+                .copy_from_slice(&self.vector[self.byte_index..self.byte_index + amount]);
+            self.byte_index += amount;
+        } else {
+            for value in &mut dest[start_index..bound_index] {
+                *value = self.read_direct_u8();
+            }
+        }
+    }
+
+    fn read_direct_u8s(&mut self, amount: usize) -> Vec<u8> {
+        if self.bool_index == 0 {
+            let result = self.vector[self.byte_index..self.byte_index + amount].to_vec();
+            self.byte_index += amount;
+            result
+        } else {
+            let mut result = Vec::with_capacity(amount);
+            for _ in 0..amount {
+                result.push(self.read_direct_u8());
+            }
+            result
+        }
     }
 }
 
@@ -1721,6 +2638,7 @@ impl U8VecBitInput {
      */
     pub fn new(vector: Vec<u8>) -> U8VecBitInput {
         U8VecBitInput {
+            bit_len: 8 * vector.len(),
             vector: vector,
             byte_index: 0,
             bool_index: 0,
@@ -1733,11 +2651,34 @@ impl U8VecBitInput {
      */
     pub fn with_start_index(vector: Vec<u8>, start_index: usize) -> U8VecBitInput {
         U8VecBitInput {
+            bit_len: 8 * vector.len(),
             vector: vector,
             byte_index: start_index,
             bool_index: 0,
         }
     }
+
+    /**
+     * Creates a new U8VecBitInput that will read from the given vector, but will not read more than the first
+     * bit_len bits of the vector. Attempts to read the remaining bits (the padding) will result in an
+     * InputCapacityError. The bit length can be obtained from the bit_len method of the bit output that
+     * created the vector. This function will panic if bit_len is larger than the number of bits in the vector.
+     */
+    pub fn with_bit_len(vector: Vec<u8>, bit_len: usize) -> U8VecBitInput {
+        if bit_len > 8 * vector.len() {
+            panic!(
+                "The bit length is {}, but the vector has only {} bits",
+                bit_len,
+                8 * vector.len()
+            );
+        }
+        U8VecBitInput {
+            vector,
+            byte_index: 0,
+            bool_index: 0,
+            bit_len,
+        }
+    }
 }
 
 /**
@@ -1749,6 +2690,8 @@ pub struct U8VecRefBitInput<'a> {
     vector: &'a Vec<u8>,
     byte_index: usize,
     bool_index: usize,
+    // The number of bits of the vector that can be read, the remaining bits are padding
+    bit_len: usize,
 }
 
 impl<'a> BitInput for U8VecRefBitInput<'a> {
@@ -1792,11 +2735,13 @@ impl<'a> BitInput for U8VecRefBitInput<'a> {
     }
 
     fn ensure_extra_capacity(&mut self, boolean_amount: usize) -> Result<(), InputCapacityError> {
-        let remaining = 8 * (self.vector.len() - self.byte_index) - self.bool_index;
+        let remaining = self
+            .bit_len
+            .saturating_sub(8 * self.byte_index + self.bool_index);
         if remaining < boolean_amount {
             Err(InputCapacityError {
                 current_capacity: self.bool_index + 8 * self.byte_index,
-                max_capacity: 8 * self.vector.len(),
+                max_capacity: self.bit_len,
                 requested_extra_capacity: boolean_amount,
             })
         } else {
@@ -1807,6 +2752,34 @@ impl<'a> BitInput for U8VecRefBitInput<'a> {
     fn terminate(&mut self) {
         // We don't own the vector, so we can't clear it
     }
+
+    fn read_direct_u8s_to_slice(&mut self, dest: &mut [u8], start_index: usize, amount: usize) {
+        let bound_index = start_index + amount;
+        if self.bool_index == 0 {
+            // The bytes can be copied directly when this bit input is byte-aligned
+            dest[start_index..bound_index]
+                .copy_from_slice(&self.vector[self.byte_index..self.byte_index + amount]);
+            self.byte_index += amount;
+        } else {
+            for value in &mut dest[start_index..bound_index] {
+                *value = self.read_direct_u8();
+            }
+        }
+    }
+
+    fn read_direct_u8s(&mut self, amount: usize) -> Vec<u8> {
+        if self.bool_index == 0 {
+            let result = self.vector[self.byte_index..self.byte_index + amount].to_vec();
+            self.byte_index += amount;
+            result
+        } else {
+            let mut result = Vec::with_capacity(amount);
+            for _ in 0..amount {
+                result.push(self.read_direct_u8());
+            }
+            result
+        }
+    }
 }
 
 impl<'a> U8VecRefBitInput<'a> {
@@ -1815,6 +2788,7 @@ impl<'a> U8VecRefBitInput<'a> {
      */
     pub fn new(vector: &Vec<u8>) -> U8VecRefBitInput {
         U8VecRefBitInput {
+            bit_len: 8 * vector.len(),
             vector: vector,
             byte_index: 0,
             bool_index: 0,
@@ -1827,9 +2801,66 @@ impl<'a> U8VecRefBitInput<'a> {
      */
     pub fn with_start_index(vector: &Vec<u8>, start_index: usize) -> U8VecRefBitInput {
         U8VecRefBitInput {
+            bit_len: 8 * vector.len(),
             vector: vector,
             byte_index: start_index,
             bool_index: 0,
         }
     }
+
+    /**
+     * Creates a new U8VecRefBitInput that will read from the given vector, but will not read more than the first
+     * bit_len bits of the vector. Attempts to read the remaining bits (the padding) will result in an
+     * InputCapacityError. The bit length can be obtained from the bit_len method of the bit output that
+     * created the vector. This function will panic if bit_len is larger than the number of bits in the vector.
+     */
+    pub fn with_bit_len(vector: &'a Vec<u8>, bit_len: usize) -> U8VecRefBitInput<'a> {
+        if bit_len > 8 * vector.len() {
+            panic!(
+                "The bit length is {}, but the vector has only {} bits",
+                bit_len,
+                8 * vector.len()
+            );
+        }
+        U8VecRefBitInput {
+            vector,
+            byte_index: 0,
+            bool_index: 0,
+            bit_len,
+        }
+    }
+
+    /**
+     * Creates a new U8VecRefBitInput that will start reading at the bit with index start_bit of the given vector
+     * and will not read the bit with index end_bit or any bit after it. This can be used to read a part of the
+     * vector that doesn't start at a byte boundary. This function will panic if start_bit is larger than end_bit
+     * or if end_bit is larger than the number of bits in the vector.
+     */
+    pub fn with_bit_range(
+        vector: &'a Vec<u8>,
+        start_bit: usize,
+        end_bit: usize,
+    ) -> U8VecRefBitInput<'a> {
+        if start_bit > end_bit || end_bit > 8 * vector.len() {
+            panic!(
+                "The bit range [{}, {}> is invalid for a vector of {} bits",
+                start_bit,
+                end_bit,
+                8 * vector.len()
+            );
+        }
+        U8VecRefBitInput {
+            vector,
+            byte_index: start_bit / 8,
+            bool_index: start_bit % 8,
+            bit_len: end_bit,
+        }
+    }
+
+    /**
+     * Returns the index of the next bit that will be read, counted from the start of the vector.
+     */
+    pub fn read_index(&self) -> usize {
+        8 * self.byte_index + self.bool_index
+    }
 }
@@ -1,13 +1,45 @@
+pub mod alphabet;
+pub mod bit_vec;
+pub mod checksum;
+pub mod chunked;
+pub mod codegen;
+pub mod container;
 pub mod converter;
+pub mod fec;
+pub mod framing;
+pub mod huffman;
+pub mod incremental;
 pub mod input;
 pub mod output;
+pub mod packed;
+pub mod parallel;
+pub mod range_coder;
+pub mod rank_select;
+pub mod record;
+pub mod schema;
 
 #[cfg(test)]
 mod tests {
 
+    use crate::alphabet::*;
+    use crate::bit_vec::*;
+    use crate::checksum::*;
+    use crate::chunked::*;
+    use crate::codegen::*;
+    use crate::container::*;
     use crate::converter::*;
+    use crate::fec::*;
+    use crate::framing::*;
+    use crate::huffman::*;
+    use crate::incremental::*;
     use crate::input::*;
     use crate::output::*;
+    use crate::packed::*;
+    use crate::parallel::*;
+    use crate::range_coder::*;
+    use crate::rank_select::*;
+    use crate::record::*;
+    use crate::schema::*;
 
     #[test]
     fn int8s_to_booleans() {
@@ -612,7 +644,7 @@ mod tests {
         output.add_some_bools_from_vec(&vec![true, false, false, true], 1, 2);
 
         output.add_string(Some(&String::from("𝄞music")));
-        output.add_string(None);
+        output.add_string(None::<&str>);
 
         output.add_i8_slice(&[-42, 11, 127, 100, 0, -21]);
         output.add_i8_vec(&vec![36, -128, -45, 96]);
@@ -861,6 +893,7 @@ mod tests {
             BitInputError::StringLength(_) => panic!("Should have been capacity error"),
             BitInputError::InputCapacity(c) => assert_eq!(c.requested_extra_capacity(), 32),
             BitInputError::InvalidString(_) => panic!("Should have been capacity error"),
+            _ => panic!("Should have been capacity error"),
         };
     }
 
@@ -897,4 +930,2043 @@ mod tests {
         let reverted = bools_to_sized_u64(64, &as_bools, 0);
         assert_eq!(integer, reverted);
     }
+
+    #[test]
+    fn test_rust_string() {
+        let strings = ["", "hello", "𝄞music", "ünïcödé and ASCII", "日本語のテキスト"];
+
+        let mut output = U8VecBitOutput::with_capacity(10);
+        for string in &strings {
+            output.add_rust_string(string);
+        }
+        // Also test the strings when the output is not byte-aligned
+        output.add_bool(true);
+        for string in &strings {
+            output.add_rust_string(string);
+        }
+        output.terminate();
+
+        let mut input = U8VecBitInput::new(output.vector);
+        for string in &strings {
+            assert_eq!(input.read_rust_string(100).unwrap(), *string);
+        }
+        assert!(input.read_bool().unwrap());
+        for string in &strings {
+            assert_eq!(input.read_rust_string(100).unwrap(), *string);
+        }
+
+        let mut bool_output = BoolVecBitOutput::new(100);
+        bool_output.add_rust_string("too long");
+        bool_output.add_var_u64(2);
+        bool_output.add_u8s_from_slice(&[0xC3, 0x28]);
+        let mut bool_input = BoolSliceBitInput::new(bool_output.get_slice());
+        match bool_input.read_rust_string(7).unwrap_err() {
+            BitInputError::StringLength(_) => {}
+            other => panic!("Expected StringLengthError, but got {:?}", other),
+        }
+        bool_input.read_u8s(8).unwrap();
+        assert_eq!(
+            bool_input.read_rust_string(2),
+            Err(BitInputError::InvalidString(InvalidStringError))
+        );
+    }
+
+    #[test]
+    fn test_ascii_string() {
+        let mut output = I8VecBitOutput::with_capacity(10);
+        output.add_ascii_string("Hello, World!");
+        output.add_ascii_string("");
+        output.add_bool(false);
+        output.add_ascii_string("\u{0}\u{7F}~");
+        output.terminate();
+
+        // (6 + 4) + 13 * 7 + (6 + 1) + 1 + (6 + 2) + 3 * 7 = 138 bits, which is rounded up to 18 bytes
+        assert_eq!(output.vector.len(), 18);
+
+        let mut input = I8VecBitInput::new(output.vector);
+        assert_eq!(input.read_ascii_string(13).unwrap(), "Hello, World!");
+        assert_eq!(input.read_ascii_string(0).unwrap(), "");
+        assert!(!input.read_bool().unwrap());
+        assert_eq!(input.read_ascii_string(3).unwrap(), "\u{0}\u{7F}~");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_non_ascii_string() {
+        BoolVecBitOutput::new(10).add_ascii_string("ü");
+    }
+
+    #[test]
+    fn test_string_boundary_lengths() {
+        let lengths = [0, 1, 252, 253, 254, 255, 256, 65535, 65536];
+        let mut strings = Vec::new();
+        for length in &lengths {
+            let string: String = (0..*length)
+                .map(|index| (b'a' + (index % 26) as u8) as char)
+                .collect();
+            strings.push(string);
+        }
+        // Surrogate pairs take 2 UTF-16 code units each, so these strings have 253, 254 and 255 units
+        let mut surrogate_strings = Vec::new();
+        for length in &[253, 254, 255] {
+            let mut string = "x".repeat(length % 2);
+            string.push_str(&"𝄞".repeat(length / 2));
+            surrogate_strings.push(string);
+        }
+
+        let mut output = U8VecBitOutput::with_capacity(100);
+        for string in strings.iter().chain(surrogate_strings.iter()) {
+            output.add_string(Some(string));
+            output.add_bool(true);
+        }
+        output.add_string(Some("😀 and ☃"));
+        output.terminate();
+
+        let mut input = U8VecBitInput::new(output.vector);
+        for string in strings.iter().chain(surrogate_strings.iter()) {
+            assert_eq!(input.read_string(65536).unwrap().as_ref(), Some(string));
+            assert!(input.read_bool().unwrap());
+        }
+        assert_eq!(
+            input.read_string(10).unwrap(),
+            Some(String::from("😀 and ☃"))
+        );
+    }
+
+    #[test]
+    fn test_string_java_format() {
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_string(None::<&str>);
+        output.add_string(Some(""));
+        output.add_string(Some("ab"));
+        output.add_string(Some(&String::from("aaaa")));
+        output.add_string(Some(&"c".repeat(254)));
+
+        // This is the layout that is used by the java BitHelper
+        let mut expected = BoolVecBitOutput::new(100);
+        expected.add_i8(0);
+        expected.add_i8(1);
+        expected.add_i8(3);
+        expected.add_u16(97);
+        expected.add_sized_u64(1, 5);
+        expected.add_bools_from_slice(&[false, true]);
+        expected.add_i8(5);
+        expected.add_u16(97);
+        expected.add_sized_u64(0, 5);
+        expected.add_i8(-1);
+        expected.add_i32(254);
+        expected.add_u16(99);
+        expected.add_sized_u64(0, 5);
+
+        assert_eq!(output.get_vec(), expected.get_vec());
+
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(input.read_string(0), Ok(None));
+        assert_eq!(input.read_string(0), Ok(Some(String::new())));
+        assert_eq!(input.read_string(2), Ok(Some(String::from("ab"))));
+        match input.read_string(3).unwrap_err() {
+            BitInputError::StringLength(_) => {}
+            other => panic!("Expected StringLengthError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_java_string() {
+        // A lone surrogate is not valid UTF-16
+        let mut output = BoolVecBitOutput::new(50);
+        output.add_i8(2);
+        output.add_u16(0xD800);
+        output.add_sized_u64(0, 5);
+
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(
+            input.read_string(1),
+            Err(BitInputError::InvalidString(InvalidStringError))
+        );
+
+        let mut output = BoolVecBitOutput::new(50);
+        output.add_i8(-1);
+        output.add_i32(-5);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match input.read_string(10).unwrap_err() {
+            BitInputError::StringLength(_) => {}
+            other => panic!("Expected StringLengthError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_alphabet_string() {
+        let hex = Alphabet::hex();
+        let base32 = Alphabet::base32();
+        let digits = Alphabet::new("0123456789");
+        let single = Alphabet::new("aaa");
+
+        assert_eq!(hex.max_bits_per_char(), 4);
+        assert_eq!(base32.max_bits_per_char(), 5);
+        assert_eq!(digits.max_bits_per_char(), 4);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single.max_bits_per_char(), 0);
+
+        let mut output = U8VecBitOutput::with_capacity(20);
+        output.add_alphabet_string("deadbeef0123", &hex).unwrap();
+        output.add_alphabet_string("MZXW6YTBOI", &base32).unwrap();
+        output.add_alphabet_string("9081726354", &digits).unwrap();
+        output.add_alphabet_string("aaaaa", &single).unwrap();
+        output.add_alphabet_string("", &Alphabet::new("")).unwrap();
+        assert_eq!(
+            output.add_alphabet_string("cafe babe", &hex),
+            Err(CharNotInAlphabetError::new(' '))
+        );
+        output.add_bool(true);
+        output.terminate();
+
+        let mut input = U8VecBitInput::new(output.vector);
+        assert_eq!(input.read_alphabet_string(&hex, 12).unwrap(), "deadbeef0123");
+        assert_eq!(input.read_alphabet_string(&base32, 10).unwrap(), "MZXW6YTBOI");
+        assert_eq!(input.read_alphabet_string(&digits, 10).unwrap(), "9081726354");
+        assert_eq!(input.read_alphabet_string(&single, 5).unwrap(), "aaaaa");
+        assert_eq!(input.read_alphabet_string(&Alphabet::new(""), 0).unwrap(), "");
+        assert!(input.read_bool().unwrap());
+    }
+
+    #[test]
+    fn test_alphabet_string_all_sizes() {
+        let chars: String = (0..40u8).map(|index| (b'0' + index) as char).collect();
+        for size in 1..=chars.len() {
+            let alphabet = Alphabet::new(&chars[0..size]);
+            let string: String = chars[0..size].chars().rev().collect();
+
+            let mut output = BoolVecBitOutput::new(100);
+            output.add_alphabet_string(&string, &alphabet).unwrap();
+            let mut input = BoolSliceBitInput::new(output.get_slice());
+            assert_eq!(input.read_alphabet_string(&alphabet, size).unwrap(), string);
+            input.read_bool().unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_huffman_string() {
+        let english = HuffmanTable::english();
+        assert!(std::ptr::eq(english, HuffmanTable::english()));
+        let text = "The quick brown fox jumps over the lazy dog, and then it rests in the shade.";
+
+        let mut output = U8VecBitOutput::with_capacity(100);
+        output.add_huffman_string(text, &english);
+        output.add_huffman_string("", &english);
+        output.add_huffman_string("Ünïcödé 𝄞 works too", &english);
+        output.add_huffman_bytes(&[0, 255, 128, 7], &english);
+        output.terminate();
+
+        let mut input = U8VecBitInput::new(output.vector);
+        assert_eq!(input.read_huffman_string(&english, 100).unwrap(), text);
+        assert_eq!(input.read_huffman_string(&english, 0).unwrap(), "");
+        assert_eq!(
+            input.read_huffman_string(&english, 100).unwrap(),
+            "Ünïcödé 𝄞 works too"
+        );
+        assert_eq!(
+            input.read_huffman_bytes(&english, 4).unwrap(),
+            vec![0, 255, 128, 7]
+        );
+
+        // English text should take much less space than with add_string
+        let mut huffman_output = BoolVecBitOutput::new(1000);
+        huffman_output.add_huffman_string(text, &english);
+        let mut java_output = BoolVecBitOutput::new(1000);
+        java_output.add_string(Some(text));
+        assert!(huffman_output.get_vec().len() * 4 < java_output.get_vec().len() * 3);
+    }
+
+    #[test]
+    fn test_trained_huffman_table() {
+        let samples = ["GATTACA", "CATGATTACA", "TAGCAT"];
+        let table = HuffmanTable::train(&samples);
+        assert!(table.lengths()[b'A' as usize] < table.lengths()[b'Z' as usize]);
+
+        let mut output = I8VecBitOutput::with_capacity(100);
+        output.add_bool(true);
+        output.add_huffman_table(&table);
+        for sample in &samples {
+            output.add_huffman_string(sample, &table);
+        }
+        output.terminate();
+
+        let mut input = I8VecBitInput::new(output.vector);
+        assert!(input.read_bool().unwrap());
+        let read_table = input.read_huffman_table().unwrap();
+        assert_eq!(read_table, table);
+        for sample in &samples {
+            assert_eq!(input.read_huffman_string(&read_table, 10).unwrap(), *sample);
+        }
+    }
+
+    #[test]
+    fn test_huffman_edge_cases() {
+        // With Fibonacci frequencies, the optimal code would need more than 24 bits
+        let mut frequencies = [0u64; HUFFMAN_SYMBOLS];
+        let (mut previous, mut current) = (1u64, 1u64);
+        for frequency in frequencies.iter_mut().take(40) {
+            *frequency = current;
+            let next = previous + current;
+            previous = current;
+            current = next;
+        }
+        let table = HuffmanTable::from_frequencies(&frequencies);
+        assert!(table
+            .lengths()
+            .iter()
+            .all(|length| *length as usize <= MAX_HUFFMAN_CODE_LENGTH));
+        assert_eq!(table.lengths()[40], 0);
+
+        let bytes: Vec<u8> = (0..40).collect();
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_huffman_bytes(&bytes, &table);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(input.read_huffman_bytes(&table, 40).unwrap(), bytes);
+
+        // A table with only 1 symbol
+        let mut frequencies = [0u64; HUFFMAN_SYMBOLS];
+        frequencies[b'x' as usize] = 5;
+        let single = HuffmanTable::from_frequencies(&frequencies);
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_huffman_string("xxx", &single);
+        // The only code is 0, so 1 is not a valid code
+        output.add_var_u64(1);
+        output.add_bool(true);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(input.read_huffman_string(&single, 3).unwrap(), "xxx");
+        assert_eq!(
+            input.read_huffman_string(&single, 3),
+            Err(BitInputError::InvalidCode(InvalidCodeError))
+        );
+
+        // 3 codes of length 1 are not a valid prefix code
+        let mut output = BoolVecBitOutput::new(2000);
+        for symbol in 0..HUFFMAN_SYMBOLS {
+            output.add_sized_u64(if symbol < 3 { 1 } else { 0 }, 5);
+        }
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(
+            input.read_huffman_table(),
+            Err(BitInputError::InvalidCode(InvalidCodeError))
+        );
+    }
+
+    // A simple deterministic pseudo-random number generator for the tests
+    fn next_random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn test_range_coder_bools() {
+        let mut state = 12345;
+        let bools: Vec<bool> = (0..10000)
+            .map(|_| next_random(&mut state) % 100 < 5)
+            .collect();
+
+        let mut output = U8VecBitOutput::with_capacity(1000);
+        output.add_bool(true);
+        let mut encoder = RangeEncoder::new(&mut output);
+        let mut model = AdaptiveBitModel::new();
+        for value in &bools {
+            encoder.encode_bool(&mut model, *value);
+        }
+        encoder.finish();
+        output.add_i32(-123456);
+        output.terminate();
+
+        // The entropy of these bools is roughly 0.29 bits per bool, so they should take about 360 bytes
+        assert!(output.vector.len() < 450);
+
+        let mut input = U8VecBitInput::new(output.vector);
+        assert!(input.read_bool().unwrap());
+        let mut decoder = RangeDecoder::new(&mut input).unwrap();
+        let mut model = AdaptiveBitModel::new();
+        for value in &bools {
+            assert_eq!(decoder.decode_bool(&mut model).unwrap(), *value);
+        }
+        assert_eq!(input.read_i32().unwrap(), -123456);
+    }
+
+    #[test]
+    fn test_range_coder_symbols() {
+        let mut state = 987654321;
+        let symbols: Vec<u32> = (0..5000)
+            .map(|_| match next_random(&mut state) % 10 {
+                0..=5 => 0,
+                6..=8 => 1,
+                _ => 2 + (next_random(&mut state) % 3) as u32,
+            })
+            .collect();
+
+        let mut output = BoolVecBitOutput::new(10000);
+        let mut encoder = RangeEncoder::new(&mut output);
+        let mut model = AdaptiveSymbolModel::new(5);
+        // Using a different model for each symbol makes the flags almost free
+        let mut flag_models = [AdaptiveBitModel::new(); 5];
+        for symbol in &symbols {
+            encoder.encode_symbol(&mut model, *symbol);
+            encoder.encode_bool(&mut flag_models[*symbol as usize], *symbol == 0);
+        }
+        encoder.encode_direct_bits(0xDEADBEEF, 32);
+        encoder.finish();
+
+        // The entropy of the symbols is about 1.45 bits, so they should take less than 1.6 bits each
+        assert!(output.get_vec().len() < 5000 * 8 / 5);
+
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        let mut decoder = RangeDecoder::new(&mut input).unwrap();
+        let mut model = AdaptiveSymbolModel::new(5);
+        let mut flag_models = [AdaptiveBitModel::new(); 5];
+        for symbol in &symbols {
+            assert_eq!(decoder.decode_symbol(&mut model).unwrap(), *symbol);
+            let flag_model = &mut flag_models[*symbol as usize];
+            assert_eq!(decoder.decode_bool(flag_model).unwrap(), *symbol == 0);
+        }
+        assert_eq!(decoder.decode_direct_bits(32).unwrap(), 0xDEADBEEF);
+        input.read_bool().unwrap_err();
+    }
+
+    #[test]
+    fn test_range_coder_truncated() {
+        let mut output = U8VecBitOutput::with_capacity(10);
+        let mut encoder = RangeEncoder::new(&mut output);
+        let mut model = AdaptiveBitModel::new();
+        for index in 0..100 {
+            encoder.encode_bool(&mut model, index % 3 == 0);
+        }
+        encoder.finish();
+        output.vector.truncate(output.vector.len() - 3);
+
+        let mut input = U8VecBitInput::new(output.vector);
+        let mut decoder = RangeDecoder::new(&mut input).unwrap();
+        let mut model = AdaptiveBitModel::new();
+        let result: Result<Vec<bool>, BitInputError> =
+            (0..100).map(|_| decoder.decode_bool(&mut model)).collect();
+        match result.unwrap_err() {
+            BitInputError::InputCapacity(_) => {}
+            other => panic!("Expected InputCapacityError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_universal_codes() {
+        let values = [1, 2, 3, 4, 7, 8, 100, 1 << 32, u64::MAX - 1, u64::MAX];
+        let mut output = BoolVecBitOutput::new(10);
+        for value in &values {
+            output.add_elias_gamma(*value);
+            output.add_elias_delta(*value);
+            output.add_exp_golomb(*value - 1, 0);
+            output.add_exp_golomb(*value, 3);
+            output.add_exp_golomb(*value, 64);
+            output.add_rice(*value % 1000, 4);
+            output.add_rice(*value, 64);
+        }
+        output.add_exp_golomb(0, 0);
+        output.add_rice(0, 0);
+
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        for value in &values {
+            assert_eq!(input.read_elias_gamma().unwrap(), *value);
+            assert_eq!(input.read_elias_delta().unwrap(), *value);
+            assert_eq!(input.read_exp_golomb(0).unwrap(), *value - 1);
+            assert_eq!(input.read_exp_golomb(3).unwrap(), *value);
+            assert_eq!(input.read_exp_golomb(64).unwrap(), *value);
+            assert_eq!(input.read_rice(4).unwrap(), *value % 1000);
+            assert_eq!(input.read_rice(64).unwrap(), *value);
+        }
+        assert_eq!(input.read_exp_golomb(0).unwrap(), 0);
+        assert_eq!(input.read_rice(0).unwrap(), 0);
+        input.read_bool().unwrap_err();
+    }
+
+    #[test]
+    fn test_universal_code_sizes() {
+        let mut output = BoolVecBitOutput::new(10);
+        output.add_elias_gamma(1);
+        assert_eq!(output.get_vec().len(), 1);
+        output.add_elias_gamma(5);
+        assert_eq!(output.get_vec().len(), 6);
+        output.add_elias_delta(1);
+        assert_eq!(output.get_vec().len(), 7);
+        output.add_exp_golomb(0, 2);
+        assert_eq!(output.get_vec().len(), 10);
+        output.add_rice(9, 2);
+        assert_eq!(output.get_vec().len(), 15);
+    }
+
+    #[test]
+    fn test_malformed_universal_codes() {
+        fn check(result: Result<u64, BitInputError>) {
+            match result.unwrap_err() {
+                BitInputError::InvalidCode(_) => {}
+                other => panic!("Expected InvalidCodeError, but got {:?}", other),
+            }
+        }
+        let zeros = [false; 200];
+        check(BoolSliceBitInput::new(&zeros).read_elias_gamma());
+        check(BoolSliceBitInput::new(&zeros).read_elias_delta());
+        check(BoolSliceBitInput::new(&zeros).read_exp_golomb(0));
+        check(BoolSliceBitInput::new(&zeros).read_exp_golomb(64));
+        check(BoolSliceBitInput::new(&zeros).read_rice(60));
+        check(BoolSliceBitInput::new(&zeros).read_rice(64));
+
+        // The Elias delta code of a length of 65 bits
+        let mut output = BoolVecBitOutput::new(20);
+        output.add_elias_gamma(66);
+        check(BoolSliceBitInput::new(output.get_slice()).read_elias_delta());
+
+        // A value that is slightly too large for a u64
+        let mut output = BoolVecBitOutput::new(200);
+        output.add_exp_golomb(u64::MAX, 1);
+        let mut bits = output.get_vec().clone();
+        let index = bits.len() - 2;
+        bits[index] = true;
+        check(BoolSliceBitInput::new(&bits).read_exp_golomb(1));
+
+        // Running out of input in the unary prefix is not an invalid code
+        match BoolSliceBitInput::new(&zeros[..10]).read_rice(0).unwrap_err() {
+            BitInputError::InputCapacity(_) => {}
+            other => panic!("Expected InputCapacityError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sorted_u64_seq() {
+        let mut state = 13579;
+        let mut timestamps = Vec::new();
+        let mut timestamp = 1_600_000_000_000;
+        for _ in 0..1000 {
+            timestamp += next_random(&mut state) % 50;
+            timestamps.push(timestamp);
+        }
+        let sequences = [
+            vec![],
+            vec![u64::MAX],
+            vec![0, 0, 0, 5, 5, u64::MAX],
+            vec![3, 1 << 40, 1 << 41, (1 << 41) + 1],
+            timestamps.clone(),
+        ];
+
+        for codec in [GapCodec::VarU64, GapCodec::Rice].iter() {
+            let mut output = U8VecBitOutput::with_capacity(100);
+            for sequence in &sequences {
+                output.add_sorted_u64_seq(sequence, *codec);
+            }
+            output.terminate();
+            let mut input = U8VecBitInput::new(output.vector);
+            for sequence in &sequences {
+                assert_eq!(&input.read_sorted_u64_seq(1000).unwrap(), sequence);
+            }
+        }
+
+        let mut var_output = BoolVecBitOutput::new(100);
+        var_output.add_sorted_u64_seq(&timestamps, GapCodec::VarU64);
+        let mut rice_output = BoolVecBitOutput::new(100);
+        rice_output.add_sorted_u64_seq(&timestamps, GapCodec::Rice);
+        // The gaps are uniform in [0, 50>, so the Rice code should need about 7 bits per gap
+        assert!(rice_output.get_vec().len() < 7 * 1000 + 100);
+        assert!(rice_output.get_vec().len() < var_output.get_vec().len());
+
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_sorted_u64_seq(&timestamps, GapCodec::Rice);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match input.read_sorted_u64_seq(999).unwrap_err() {
+            BitInputError::Length(_) => {}
+            other => panic!("Expected LengthError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sorted_u64_seq_overflow() {
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_var_u64(3);
+        output.add_var_u64(u64::MAX - 10);
+        output.add_bool(false);
+        output.add_var_u64(10);
+        output.add_var_u64(1);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match input.read_sorted_u64_seq(10).unwrap_err() {
+            BitInputError::IntegerOverflow(_) => {}
+            other => panic!("Expected IntegerOverflowError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unsorted_u64_seq() {
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_sorted_u64_seq(&[1, 3, 2], GapCodec::VarU64);
+    }
+
+    #[test]
+    fn test_packed_integers() {
+        let i64_slices: [&[i64]; 5] = [
+            &[],
+            &[-5],
+            &[7, 7, 7],
+            &[-3, 10, 4, -1, 12],
+            &[i64::MIN, 0, i64::MAX],
+        ];
+        let u32_slices: [&[u32]; 4] = [&[], &[0, 0], &[1000, 1003, 1001], &[0, u32::MAX]];
+
+        let mut output = U8VecBitOutput::with_capacity(100);
+        for values in i64_slices.iter() {
+            output.add_packed_i64_slice(values);
+            output.add_patched_i64_slice(values);
+        }
+        for values in u32_slices.iter() {
+            output.add_packed_u32_slice(values);
+            output.add_patched_u32_slice(values);
+        }
+        output.terminate();
+
+        let mut input = U8VecBitInput::new(output.vector);
+        for values in i64_slices.iter() {
+            assert_eq!(&input.read_packed_i64_vec(10).unwrap()[..], *values);
+            assert_eq!(&input.read_patched_i64_vec(10).unwrap()[..], *values);
+        }
+        for values in u32_slices.iter() {
+            assert_eq!(&input.read_packed_u32_vec(10).unwrap()[..], *values);
+            assert_eq!(&input.read_patched_u32_vec(10).unwrap()[..], *values);
+        }
+
+        // The values are in [1000, 1003], so every value should take 2 bits
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_packed_u32_slice(&[1000, 1003, 1001, 1002]);
+        assert_eq!(output.get_vec().len(), 9 + 32 + 7 + 4 * 2);
+    }
+
+    #[test]
+    fn test_patched_integers() {
+        let mut state = 24680;
+        let mut values: Vec<u32> = (0..1000)
+            .map(|_| 500 + (next_random(&mut state) % 16) as u32)
+            .collect();
+        values[17] = 1_000_000;
+        values[500] = 3_000_000_000;
+        values[999] = 70_000;
+
+        let mut packed_output = BoolVecBitOutput::new(100);
+        packed_output.add_packed_u32_slice(&values);
+        let mut patched_output = BoolVecBitOutput::new(100);
+        patched_output.add_patched_u32_slice(&values);
+
+        // Most values need only 4 bits, so the outliers shouldn't inflate the size of the patched output
+        assert!(patched_output.get_vec().len() < 1000 * 4 + 200);
+        assert!(packed_output.get_vec().len() > 1000 * 32);
+
+        let mut input = BoolSliceBitInput::new(patched_output.get_slice());
+        assert_eq!(input.read_patched_u32_vec(1000).unwrap(), values);
+        let mut input = BoolSliceBitInput::new(packed_output.get_slice());
+        assert_eq!(input.read_packed_u32_vec(1000).unwrap(), values);
+    }
+
+    #[test]
+    fn test_invalid_packed_integers() {
+        // A width of 33 bits is invalid for u32s
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_var_u64(1);
+        output.add_u32(0);
+        output.add_sized_u64(33, 7);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match input.read_packed_u32_vec(10).unwrap_err() {
+            BitInputError::InvalidCode(_) => {}
+            other => panic!("Expected InvalidCodeError, but got {:?}", other),
+        }
+
+        // The minimum plus the offset is larger than u32::MAX
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_var_u64(1);
+        output.add_u32(10);
+        output.add_sized_u64(32, 7);
+        output.add_sized_u64(u32::MAX as u64, 32);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match input.read_packed_u32_vec(10).unwrap_err() {
+            BitInputError::IntegerOverflow(_) => {}
+            other => panic!("Expected IntegerOverflowError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rle() {
+        let mut occupancy: Vec<bool> =
+            (0..1000).map(|index| (200..700).contains(&index)).collect();
+        occupancy[900] = true;
+        let alternating: Vec<bool> = (0..100).map(|index| index % 2 == 0).collect();
+        let mut tiles = vec![3; 500];
+        tiles.extend_from_slice(&[0, 0, 7, u32::MAX]);
+        tiles.extend_from_slice(&[12; 300]);
+        let random: Vec<u32> = (0..50).map(|index| index * 1234567).collect();
+
+        let mut output = U8VecBitOutput::with_capacity(100);
+        for bools in [&occupancy[..], &alternating, &[], &[true]].iter() {
+            output.add_rle_bools(bools);
+            output.add_auto_rle_bools(bools);
+        }
+        for values in [&tiles[..], &random, &[]].iter() {
+            output.add_rle_u32_slice(values);
+            output.add_auto_rle_u32_slice(values);
+        }
+        output.terminate();
+
+        let mut input = U8VecBitInput::new(output.vector);
+        for bools in [&occupancy[..], &alternating, &[], &[true]].iter() {
+            assert_eq!(&input.read_rle_bools(1000).unwrap()[..], *bools);
+            assert_eq!(&input.read_auto_rle_bools(1000).unwrap()[..], *bools);
+        }
+        for values in [&tiles[..], &random, &[]].iter() {
+            assert_eq!(&input.read_rle_u32_vec(1000).unwrap()[..], *values);
+            assert_eq!(&input.read_auto_rle_u32_vec(1000).unwrap()[..], *values);
+        }
+    }
+
+    #[test]
+    fn test_auto_rle_sizes() {
+        let occupancy: Vec<bool> =
+            (0..1000).map(|index| (200..700).contains(&index)).collect();
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_auto_rle_bools(&occupancy);
+        assert_eq!(output.get_vec().len(), 1 + 16 + 1 + 14 + 15 + 15);
+
+        // Run-length encoding would make these bools much bigger, so they should be stored raw
+        let alternating: Vec<bool> = (0..100).map(|index| index % 2 == 0).collect();
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_auto_rle_bools(&alternating);
+        assert_eq!(output.get_vec().len(), 1 + 13 + 100);
+
+        let random: Vec<u32> = (0..50).map(|index| index * 1234567).collect();
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_auto_rle_u32_slice(&random);
+        assert_eq!(output.get_vec().len(), 1 + 12 + 50 * 32);
+    }
+
+    #[test]
+    fn test_invalid_rle() {
+        // The run of 11 bools is longer than the stored length of 10
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_var_u64(10);
+        output.add_bool(true);
+        output.add_var_u64(10);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match input.read_rle_bools(100).unwrap_err() {
+            BitInputError::InvalidCode(_) => {}
+            other => panic!("Expected InvalidCodeError, but got {:?}", other),
+        }
+
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_var_u64(1);
+        output.add_var_u64(1 << 32);
+        output.add_var_u64(0);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match input.read_rle_u32_vec(100).unwrap_err() {
+            BitInputError::IntegerOverflow(_) => {}
+            other => panic!("Expected IntegerOverflowError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sparse_bools() {
+        let mut state = 112233;
+        let mostly_false: Vec<bool> =
+            (0..5000).map(|_| next_random(&mut state) % 1000 < 10).collect();
+        let mostly_true: Vec<bool> = mostly_false.iter().map(|value| !value).collect();
+        let all_false = vec![false; 300];
+        let bool_slices = [&mostly_false[..], &mostly_true, &all_false, &[true], &[]];
+
+        let mut bool_output = BoolVecBitOutput::new(100);
+        let mut i8_output = I8VecBitOutput::with_capacity(100);
+        let mut u8_output = U8VecBitOutput::with_capacity(100);
+        for bools in bool_slices.iter() {
+            bool_output.add_sparse_bools(bools);
+            i8_output.add_sparse_bools(bools);
+            u8_output.add_sparse_bools(bools);
+        }
+        i8_output.terminate();
+        u8_output.terminate();
+
+        let mut bool_input = BoolSliceBitInput::new(bool_output.get_slice());
+        let mut i8_input = I8VecBitInput::new(i8_output.vector);
+        let mut u8_input = U8VecBitInput::new(u8_output.vector);
+        for bools in bool_slices.iter() {
+            assert_eq!(&bool_input.read_sparse_bools(5000).unwrap()[..], *bools);
+            assert_eq!(&i8_input.read_sparse_bools(5000).unwrap()[..], *bools);
+            assert_eq!(&u8_input.read_sparse_bools(5000).unwrap()[..], *bools);
+        }
+
+        // About 50 of the 5000 bools are true, so this should take much less than 5000 bits
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_sparse_bools(&mostly_true);
+        assert!(output.get_vec().len() < 500);
+    }
+
+    #[test]
+    fn test_invalid_sparse_bools() {
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_var_u64(10);
+        output.add_bool(true);
+        output.add_sorted_u64_seq(&[3, 10], GapCodec::VarU64);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match input.read_sparse_bools(10).unwrap_err() {
+            BitInputError::InvalidCode(_) => {}
+            other => panic!("Expected InvalidCodeError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_packed_int_vec() {
+        for bits in [0, 1, 5, 13, 31, 63, 64].iter() {
+            let bits = *bits;
+            let mut state = 5555 + bits as u64;
+            let max = if bits == 64 { u64::MAX } else { (1 << bits) - 1 };
+            let values: Vec<u64> = (0..300)
+                .map(|_| if bits == 0 { 0 } else { next_random(&mut state) & max })
+                .collect();
+
+            let mut vec = PackedIntVec::from_values(bits, &values);
+            assert_eq!(vec.len(), 300);
+            assert_eq!(vec.to_vec(), values);
+            vec.set(150, max);
+            vec.set(151, 0);
+            assert_eq!(vec.get(149), values[149]);
+            assert_eq!(vec.get(150), max);
+            assert_eq!(vec.get(151), 0);
+            assert_eq!(vec.get(152), values[152]);
+            vec.push(max);
+            assert_eq!(vec.get(300), max);
+
+            // The integers should be stored exactly like add_sized_u64 would store them
+            let mut output = BoolVecBitOutput::new(100);
+            output.add_packed_int_vec(&vec);
+            let mut input = BoolSliceBitInput::new(output.get_slice());
+            assert_eq!(input.read_var_u64().unwrap(), 301);
+            assert_eq!(input.read_sized_u64(7).unwrap(), bits as u64);
+            for value in vec.iter() {
+                assert_eq!(input.read_sized_u64(bits).unwrap(), value);
+            }
+            input.read_bool().unwrap_err();
+
+            let mut output = U8VecBitOutput::with_capacity(100);
+            output.add_packed_int_vec(&vec);
+            output.add_bool(true);
+            output.terminate();
+            let mut input = U8VecBitInput::new(output.vector);
+            assert_eq!(input.read_packed_int_vec(301).unwrap(), vec);
+            assert!(input.read_bool().unwrap());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_packed_int_vec_overflow() {
+        let mut vec = PackedIntVec::with_len(4, 10);
+        vec.set(3, 16);
+    }
+
+    #[test]
+    fn test_rank_select_bit_vec() {
+        let mut state = 8642;
+        for length in [0, 1, 63, 64, 65, 511, 512, 513, 5000].iter() {
+            let bools: Vec<bool> = (0..*length).map(|_| next_random(&mut state) % 3 == 1).collect();
+            let vec = RankSelectBitVec::from_bools(&bools);
+            assert_eq!(vec.len(), bools.len());
+
+            let mut ones = Vec::new();
+            for (index, value) in bools.iter().enumerate() {
+                assert_eq!(vec.get(index), *value);
+                assert_eq!(vec.rank1(index), ones.len());
+                assert_eq!(vec.rank0(index), index - ones.len());
+                if *value {
+                    ones.push(index);
+                }
+            }
+            assert_eq!(vec.rank1(bools.len()), ones.len());
+            assert_eq!(vec.count_ones(), ones.len());
+            for (k, index) in ones.iter().enumerate() {
+                assert_eq!(vec.select1(k), Some(*index));
+            }
+            assert_eq!(vec.select1(ones.len()), None);
+
+            let mut output = U8VecBitOutput::with_capacity(100);
+            output.add_rank_select_bit_vec(&vec);
+            output.terminate();
+            assert!(output.vector.len() <= 3 + *length / 8);
+            let mut input = U8VecBitInput::new(output.vector);
+            assert_eq!(input.read_rank_select_bit_vec(5000).unwrap(), vec);
+        }
+    }
+
+    #[test]
+    fn test_rank_select_all_ones() {
+        let vec = RankSelectBitVec::from_bools(&[true; 2000]);
+        for index in 0..2000 {
+            assert_eq!(vec.rank1(index), index);
+            assert_eq!(vec.select1(index), Some(index));
+        }
+        assert_eq!(vec.select1(2000), None);
+    }
+
+    #[test]
+    fn test_bit_vec() {
+        let mut state = 97531;
+        let bools: Vec<bool> = (0..200).map(|_| next_random(&mut state) % 2 == 1).collect();
+        let mut bits = BitVec::from_bools(&bools);
+        assert_eq!(bits.len(), 200);
+        assert_eq!(bits.to_bools(), bools);
+        assert_eq!(bits.count_ones(), bools.iter().filter(|value| **value).count());
+
+        bits.set(100, !bools[100]);
+        assert_eq!(bits.get(100), !bools[100]);
+        bits.set(100, bools[100]);
+        bits.push(true);
+        assert_eq!(bits.len(), 201);
+        assert_eq!(bits.pop(), Some(true));
+        for index in (0..200).rev() {
+            assert_eq!(bits.pop(), Some(bools[index]));
+        }
+        assert_eq!(bits.pop(), None);
+        assert!(bits.is_empty());
+        assert_eq!(bits, BitVec::new());
+
+        let bits = BitVec::from_bools(&bools);
+        for (start, end) in [(0, 0), (0, 200), (3, 70), (64, 128), (63, 200), (130, 199)].iter() {
+            let slice = bits.slice(*start..*end);
+            assert_eq!(slice.to_bools(), &bools[*start..*end]);
+        }
+    }
+
+    #[test]
+    fn test_bit_vec_operators() {
+        let a = BitVec::from_bools(&[true, true, false, false, true]);
+        let b = BitVec::from_bools(&[true, false, true, false, true]);
+        assert_eq!(&a & &b, BitVec::from_bools(&[true, false, false, false, true]));
+        assert_eq!(&a | &b, BitVec::from_bools(&[true, true, true, false, true]));
+        assert_eq!(&a ^ &b, BitVec::from_bools(&[false, true, true, false, false]));
+        assert_eq!(!&a, BitVec::from_bools(&[false, false, true, true, false]));
+        assert_eq!(!&BitVec::filled(70, true), BitVec::filled(70, false));
+        assert_eq!((!&BitVec::filled(70, false)).count_ones(), 70);
+
+        let mut c = a.clone();
+        c ^= &a;
+        assert_eq!(c, BitVec::filled(5, false));
+        assert_eq!(format!("{:?}", a), "BitVec(11001)");
+    }
+
+    #[test]
+    fn test_bit_vec_bit_io() {
+        let mut bits = BitVec::new();
+        bits.add_bool(true);
+        bits.add_i8(-37);
+        bits.add_sized_u64(12345, 17);
+        bits.add_rust_string("BitVec");
+        bits.add_var_u64(u64::MAX);
+        assert_eq!(bits.len(), 1 + 8 + 17 + 9 + 6 * 8 + 70);
+
+        let mut input = BitVecBitInput::new(&bits);
+        assert!(input.read_bool().unwrap());
+        assert_eq!(input.read_i8().unwrap(), -37);
+        assert_eq!(input.read_sized_u64(17).unwrap(), 12345);
+        assert_eq!(input.read_rust_string(10).unwrap(), "BitVec");
+        assert_eq!(input.read_var_u64().unwrap(), u64::MAX);
+        assert_eq!(input.read_index(), bits.len());
+        input.read_bool().unwrap_err();
+
+        // The bits should be exactly the same as the bits of a BoolVecBitOutput
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_bool(true);
+        output.add_i8(-37);
+        output.add_sized_u64(12345, 17);
+        output.add_rust_string("BitVec");
+        output.add_var_u64(u64::MAX);
+        assert_eq!(&bits.to_bools(), output.get_vec());
+    }
+
+    #[test]
+    fn test_bit_len() {
+        let mut bool_output = BoolVecBitOutput::new(10);
+        let mut i8_output = I8VecBitOutput::with_capacity(10);
+        let mut u8_output = U8VecBitOutput::with_capacity(10);
+        let mut bit_vec = BitVec::new();
+        {
+            let outputs: [&mut dyn BitOutput; 4] =
+                [&mut bool_output, &mut i8_output, &mut u8_output, &mut bit_vec];
+            for output in outputs {
+                assert_eq!(output.bit_len(), 0);
+                output.add_bool(true);
+                assert_eq!(output.bit_len(), 1);
+                output.add_i8(-5);
+                assert_eq!(output.bit_len(), 9);
+                output.add_sized_u64(3, 7);
+                assert_eq!(output.bit_len(), 16);
+                output.add_u8s_from_slice(&[1, 2, 3]);
+                assert_eq!(output.bit_len(), 40);
+                output.add_bool(false);
+                assert_eq!(output.bit_len(), 41);
+            }
+        }
+
+        let (bytes, bit_len) = u8_output.into_bytes_with_bit_len();
+        assert_eq!((bytes.len(), bit_len), (6, 41));
+        let (i8s, i8_bit_len) = i8_output.into_bytes_with_bit_len();
+        assert_eq!((i8s.len(), i8_bit_len), (6, 41));
+
+        let mut input = U8VecBitInput::with_bit_len(bytes.clone(), bit_len);
+        input.read_bools(41).unwrap();
+        let error = input.read_bool().unwrap_err();
+        assert_eq!(error, BitInputError::InputCapacity(InputCapacityError::new(41, 41, 1)));
+        let mut input = U8VecRefBitInput::with_bit_len(&bytes, bit_len);
+        input.read_bools(40).unwrap();
+        input.read_u8().unwrap_err();
+        let mut input = I8VecBitInput::with_bit_len(i8s, i8_bit_len);
+        input.read_bools(41).unwrap();
+        let error = input.read_bool().unwrap_err();
+        assert_eq!(error, BitInputError::InputCapacity(InputCapacityError::new(41, 41, 1)));
+
+        // Without the bit length, the padding can be read
+        let mut input = U8VecBitInput::new(bytes);
+        input.read_bools(48).unwrap();
+        input.read_bool().unwrap_err();
+    }
+
+    #[test]
+    fn test_append_output() {
+        for first_bits in 0..20 {
+            for second_bits in [0, 1, 7, 8, 9, 30].iter() {
+                let mut state = 1000 * first_bits as u64 + *second_bits as u64;
+                let bools: Vec<bool> = (0..first_bits + second_bits)
+                    .map(|_| next_random(&mut state) % 2 == 1)
+                    .collect();
+
+                let mut first_u8 = U8VecBitOutput::with_capacity(1);
+                let mut second_u8 = U8VecBitOutput::with_capacity(1);
+                let mut first_i8 = I8VecBitOutput::with_capacity(1);
+                let mut second_i8 = I8VecBitOutput::with_capacity(1);
+                first_u8.add_bools_from_slice(&bools[..first_bits]);
+                second_u8.add_bools_from_slice(&bools[first_bits..]);
+                first_i8.add_bools_from_slice(&bools[..first_bits]);
+                second_i8.add_bools_from_slice(&bools[first_bits..]);
+                first_u8.append_output(&second_u8);
+                first_i8.append_output(&second_i8);
+                assert_eq!(first_u8.bit_len(), bools.len());
+                assert_eq!(first_i8.bit_len(), bools.len());
+
+                // The result should be exactly the same as a sequential encode
+                let mut sequential = U8VecBitOutput::with_capacity(1);
+                sequential.add_bools_from_slice(&bools);
+                sequential.add_i8(-123);
+                first_u8.add_i8(-123);
+                first_i8.add_i8(-123);
+                assert_eq!(first_u8.vector, sequential.vector);
+                let i8_vector: Vec<i8> = sequential.vector.iter().map(|byte| *byte as i8).collect();
+                assert_eq!(first_i8.vector, i8_vector);
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_bits_from_input() {
+        let mut source = U8VecBitOutput::with_capacity(10);
+        source.add_bool(true);
+        source.add_i32(123456789);
+        source.add_sized_u64(77, 11);
+        source.add_rust_string("copy me");
+        let (bytes, bit_len) = source.into_bytes_with_bit_len();
+
+        let mut input = U8VecBitInput::with_bit_len(bytes, bit_len);
+        assert!(input.read_bool().unwrap());
+        let mut output = BitVec::new();
+        output.add_bool(false);
+        output.add_bits_from_input(&mut input, bit_len - 1).unwrap();
+        output.add_bits_from_input(&mut input, 1).unwrap_err();
+
+        let mut input = BitVecBitInput::new(&output);
+        assert!(!input.read_bool().unwrap());
+        assert_eq!(input.read_i32().unwrap(), 123456789);
+        assert_eq!(input.read_sized_u64(11).unwrap(), 77);
+        assert_eq!(input.read_rust_string(10).unwrap(), "copy me");
+        input.read_bool().unwrap_err();
+    }
+
+    #[test]
+    fn test_encode_parallel() {
+        // Every item takes a different number of bits, so the chunks won't be byte-aligned
+        let items: Vec<(u64, String)> = (0..1000)
+            .map(|index| (index * index, "x".repeat(index as usize % 7)))
+            .collect();
+        let encode = |output: &mut U8VecBitOutput, item: &(u64, String)| {
+            output.add_var_u64(item.0);
+            output.add_ascii_string(&item.1);
+            output.add_bool(item.1.is_empty());
+        };
+
+        let mut sequential = U8VecBitOutput::with_capacity(100);
+        for item in &items {
+            encode(&mut sequential, item);
+        }
+        for thread_count in [0, 1, 3, 8, 50].iter() {
+            let output = encode_parallel(&items, *thread_count, encode);
+            assert_eq!(output.vector, sequential.vector);
+            assert_eq!(output.bit_len(), sequential.bit_len());
+        }
+
+        let (output, offsets) = encode_parallel_with_offsets(&items, 4, encode);
+        assert_eq!(output.vector, sequential.vector);
+        assert_eq!(offsets.len(), 4);
+        for offset in &offsets {
+            let mut input = U8VecRefBitInput::new(&output.vector);
+            input.read_bools(offset.bit_offset).unwrap();
+            let item = &items[offset.first_item];
+            assert_eq!(input.read_var_u64().unwrap(), item.0);
+            assert_eq!(input.read_ascii_string(10).unwrap(), item.1);
+        }
+
+        let (output, offsets) = encode_parallel_with_offsets(&[] as &[(u64, String)], 4, encode);
+        assert_eq!(output.bit_len(), 0);
+        assert!(offsets.is_empty());
+
+        // With the offset table in the output, the result can be decoded on its own
+        let decode = |input: &mut U8VecRefBitInput| -> Result<(u64, String), BitInputError> {
+            let item = (input.read_var_u64()?, input.read_ascii_string(10)?);
+            input.read_bool()?;
+            Ok(item)
+        };
+        for thread_count in [0, 1, 3, 8, 50].iter() {
+            let bytes = encode_parallel_chunked(&items, *thread_count, encode);
+            let reader = ChunkedReader::new(bytes).unwrap();
+            assert_eq!(reader.record_count(), items.len());
+            assert_eq!(reader.decode_record(17, decode).unwrap(), items[17]);
+            assert_eq!(reader.decode_all_parallel(3, decode).unwrap(), items);
+        }
+        let bytes = encode_parallel_chunked(&[] as &[(u64, String)], 4, encode);
+        assert_eq!(ChunkedReader::new(bytes).unwrap().record_count(), 0);
+    }
+
+    #[test]
+    fn test_chunked_container() {
+        let write = |output: &mut U8VecBitOutput, index: u64| {
+            output.add_var_u64(index * 37);
+            output.add_ascii_string(&"r".repeat(index as usize % 5));
+        };
+        let decode = |input: &mut U8VecRefBitInput| -> Result<(u64, String), BitInputError> {
+            Ok((input.read_var_u64()?, input.read_ascii_string(10)?))
+        };
+        let expected = |index: u64| (index * 37, "r".repeat(index as usize % 5));
+
+        let mut writer = ChunkedWriter::new(16);
+        for index in 0..1000 {
+            writer.add_record(|output| write(output, index));
+        }
+        assert_eq!(writer.record_count(), 1000);
+        let reader = ChunkedReader::new(writer.finish()).unwrap();
+        assert_eq!(reader.record_count(), 1000);
+        assert_eq!(reader.records_per_chunk(), 16);
+        assert_eq!(reader.chunk_count(), 63);
+
+        for index in [0, 15, 16, 500, 999].iter() {
+            assert_eq!(reader.decode_record(*index, decode).unwrap(), expected(*index as u64));
+        }
+        let last_chunk = reader.decode_chunk(62, decode).unwrap();
+        assert_eq!(last_chunk.len(), 8);
+        assert_eq!(last_chunk[7], expected(999));
+
+        // A chunk input can't read beyond its own chunk
+        let mut input = reader.chunk_input(3);
+        for _ in 0..16 {
+            decode(&mut input).unwrap();
+        }
+        decode(&mut input).unwrap_err();
+
+        let all: Vec<_> = (0..1000).map(expected).collect();
+        for thread_count in [0, 1, 4, 100].iter() {
+            assert_eq!(reader.decode_all_parallel(*thread_count, decode).unwrap(), all);
+        }
+
+        let empty = ChunkedReader::new(ChunkedWriter::new(4).finish()).unwrap();
+        assert_eq!(empty.chunk_count(), 0);
+        assert!(empty.decode_all_parallel(2, decode).unwrap().is_empty());
+
+        let mut writer = ChunkedWriter::new(3);
+        for index in 0..10 {
+            writer.add_record(|output| write(output, index));
+        }
+        let mut bytes = writer.finish();
+        let last_index = bytes.len() - 1;
+        bytes[last_index] = bytes[last_index].wrapping_add(1);
+        assert!(ChunkedReader::new(bytes).is_err());
+        assert!(ChunkedReader::new(vec![0; 5]).is_err());
+
+        // Crafted tables must not make the reader allocate memory for records that don't exist
+        let crafted_table = |record_count: u64, data_bits: usize| {
+            let mut output = U8VecBitOutput::with_capacity(0);
+            output.add_bools_from_slice(&vec![false; data_bits]);
+            output.add_var_u64(record_count);
+            output.add_var_u64(record_count);
+            output.add_var_u64(data_bits as u64);
+            output.add_sorted_u64_seq(&[0], GapCodec::Rice);
+            while output.bit_len() & 7 != 0 {
+                output.add_bool(false);
+            }
+            output.add_u64((data_bits / 8) as u64);
+            output.terminate();
+            output.vector
+        };
+        assert!(ChunkedReader::new(crafted_table(1 << 40, 0)).is_err());
+        assert!(ChunkedReader::new(crafted_table(1 << 40, 8)).is_err());
+        assert!(ChunkedReader::with_empty_records(crafted_table(1 << 40, 0), 1000).is_err());
+
+        // Records that take 0 bits need with_empty_records
+        let mut writer = ChunkedWriter::new(2);
+        for _ in 0..5 {
+            writer.add_record(|_| {});
+        }
+        let bytes = writer.finish();
+        assert!(ChunkedReader::new(bytes.clone()).is_err());
+        assert!(ChunkedReader::with_empty_records(bytes.clone(), 4).is_err());
+        let reader = ChunkedReader::with_empty_records(bytes, 5).unwrap();
+        assert_eq!(reader.decode_all_parallel(2, |_| Ok(())).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_incremental_bit_input() {
+        let mut output = U8VecBitOutput::with_capacity(100);
+        for index in 0..50 {
+            output.add_var_u64(index * 1000);
+            output.add_string(Some(&"msg".repeat(index as usize % 4)));
+            output.add_bool(index % 3 == 0);
+        }
+        output.add_var_u64(5);
+        output.terminate();
+        let bytes = output.vector;
+
+        let parse = |input: &mut IncrementalBitInput| -> Result<_, BitInputError> {
+            Ok((input.read_var_u64()?, input.read_string(20)?, input.read_bool()?))
+        };
+        for chunk_size in [1, 3, 16, 1000].iter() {
+            let mut input = IncrementalBitInput::new();
+            let mut messages = Vec::new();
+            let mut incomplete_count = 0;
+            for chunk in bytes.chunks(*chunk_size) {
+                input.feed(chunk);
+                loop {
+                    match input.try_parse(parse) {
+                        Ok(message) => messages.push(message),
+                        Err(BitInputError::NeedMoreData(error)) => {
+                            assert!(error.missing_bits() > 0);
+                            incomplete_count += 1;
+                            break;
+                        }
+                        Err(other) => panic!("Unexpected error {:?}", other),
+                    }
+                }
+            }
+            assert!(incomplete_count > 0);
+            assert_eq!(messages.len(), 50);
+            for (index, message) in messages.iter().enumerate() {
+                assert_eq!(message.0, index as u64 * 1000);
+                assert_eq!(message.1.as_ref().unwrap(), &"msg".repeat(index % 4));
+                assert_eq!(message.2, index % 3 == 0);
+            }
+
+            // The last message is incomplete and will never be completed after finish
+            input.finish();
+            match input.try_parse(parse).unwrap_err() {
+                BitInputError::InputCapacity(_) => {}
+                other => panic!("Expected InputCapacityError, but got {:?}", other),
+            }
+            assert_eq!(input.read_var_u64().unwrap(), 5);
+        }
+
+        // Other errors roll back as well
+        let mut output = U8VecBitOutput::with_capacity(10);
+        output.add_string(Some("too long"));
+        output.terminate();
+        let mut input = IncrementalBitInput::new();
+        input.feed(&output.vector);
+        let available_bits = input.available_bits();
+        match input.try_parse(|input| input.read_string(3)).unwrap_err() {
+            BitInputError::StringLength(_) => {}
+            other => panic!("Expected StringLengthError, but got {:?}", other),
+        }
+        assert_eq!(input.available_bits(), available_bits);
+        assert_eq!(input.try_parse(|input| input.read_string(10)).unwrap().unwrap(), "too long");
+
+        // Feeding more data can't solve the capacity errors of bounded inputs inside the parse
+        input.feed(&[1, 2, 3, 4, 5]);
+        let available_bits = input.available_bits();
+        let parse_inner = |input: &mut IncrementalBitInput| {
+            let bytes = vec![input.read_u8()?, input.read_u8()?];
+            U8VecBitInput::new(bytes).read_u32()
+        };
+        match input.try_parse(parse_inner).unwrap_err() {
+            BitInputError::InputCapacity(error) => assert_eq!(error.max_capacity(), 16),
+            other => panic!("Expected InputCapacityError, but got {:?}", other),
+        }
+        assert_eq!(input.available_bits(), available_bits);
+    }
+
+    #[test]
+    fn test_framing_over_tcp() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // The server echoes every frame back with the number of the frame added to it
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let buffered = std::io::BufReader::new(stream.try_clone().unwrap());
+            let mut reader = FrameReader::new(buffered, 1000);
+            let mut writer = FrameWriter::new(stream);
+            let mut frame_count = 0;
+            while let Some(mut input) = reader.read_frame().unwrap() {
+                let text = input.read_string(100).unwrap().unwrap();
+                let flag = input.read_bool().unwrap();
+                input.read_bool().unwrap_err();
+
+                let mut output = BitVec::new();
+                output.add_var_u64(frame_count);
+                output.add_string(Some(&text));
+                output.add_bool(flag);
+                writer.write_frame(&output).unwrap();
+                writer.flush().unwrap();
+                frame_count += 1;
+            }
+            frame_count
+        });
+
+        let stream = std::net::TcpStream::connect(address).unwrap();
+        let buffered = std::io::BufReader::new(stream.try_clone().unwrap());
+        let mut reader = FrameReader::new(buffered, 1000);
+        let mut writer = FrameWriter::new(stream);
+        for index in 0..20 {
+            let text = "frame".repeat(index);
+            let mut output = U8VecBitOutput::with_capacity(10);
+            output.add_string(Some(&text));
+            output.add_bool(index % 2 == 0);
+            writer.write_frame(&output).unwrap();
+            writer.flush().unwrap();
+
+            let mut input = reader.read_frame().unwrap().unwrap();
+            assert_eq!(input.read_var_u64().unwrap(), index as u64);
+            assert_eq!(input.read_string(100).unwrap().unwrap(), text);
+            assert_eq!(input.read_bool().unwrap(), index % 2 == 0);
+            input.read_bool().unwrap_err();
+        }
+        writer.get_mut().shutdown(std::net::Shutdown::Write).unwrap();
+        assert!(reader.read_frame().unwrap().is_none());
+        assert_eq!(server.join().unwrap(), 20);
+    }
+
+    #[test]
+    fn test_framing_errors() {
+        let mut output = U8VecBitOutput::with_capacity(10);
+        for value in 1..=5 {
+            output.add_u64(value);
+        }
+        let mut writer = FrameWriter::new(Vec::new());
+        writer.write_frame(&output).unwrap();
+        let bytes = writer.into_inner();
+        assert_eq!(bytes.len(), 2 + 40);
+
+        // All bit outputs must create the same frames
+        let mut outputs: Vec<Box<dyn FrameOutput>> = vec![
+            Box::new(U8VecBitOutput::with_capacity(0)),
+            Box::new(I8VecBitOutput::with_capacity(0)),
+            Box::new(BoolVecBitOutput::new(0)),
+            Box::new(BitVec::new()),
+        ];
+        let mut frames = Vec::new();
+        for output in &mut outputs {
+            output.add_i16(-1234);
+            output.add_bool(true);
+            output.add_sized_u64(5, 3);
+            let mut writer = FrameWriter::new(Vec::new());
+            writer.write_frame(output.as_ref()).unwrap();
+            frames.push(writer.into_inner());
+        }
+        assert_eq!(frames[0].len(), 1 + 3);
+        assert!(frames.iter().all(|frame| *frame == frames[0]));
+
+        let read_error = |bytes: &[u8], max_frame_bytes| {
+            match FrameReader::new(bytes, max_frame_bytes).read_frame() {
+                Ok(_) => panic!("Reading the frame should have failed"),
+                Err(error) => error,
+            }
+        };
+        match read_error(&bytes, 39) {
+            FrameError::FrameTooLarge { frame_bytes, .. } => assert_eq!(frame_bytes, 40),
+            other => panic!("Expected FrameTooLarge, but got {:?}", other),
+        }
+        match read_error(&bytes[..30], 40) {
+            FrameError::Io(error) => assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof),
+            other => panic!("Expected an Io error, but got {:?}", other),
+        }
+        match read_error(&[0xFF; 11], 40) {
+            FrameError::InvalidHeader => {}
+            other => panic!("Expected InvalidHeader, but got {:?}", other),
+        }
+        let mut reader = FrameReader::new(&bytes[..], 40);
+        assert_eq!(reader.read_frame().unwrap().unwrap().read_u64().unwrap(), 1);
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(&[255; 100000]), 0x149A_302C);
+    }
+
+    #[test]
+    fn test_checksummed_bit_io() {
+        let output = U8VecBitOutput::with_capacity(10);
+        let mut first = ChecksummedBitOutput::new(output, ChecksumAlgorithm::Crc32);
+        first.add_i32(-123456);
+        first.add_string(Some("checked"));
+        first.add_bool(true);
+        assert_eq!(first.bit_len(), 90);
+        let output = first.finish();
+        let mut second = ChecksummedBitOutput::new(output, ChecksumAlgorithm::Adler32);
+        second.add_var_u64(98765);
+        second.terminate();
+        let (bytes, bit_len) = second.into_inner().into_bytes_with_bit_len();
+
+        let mut input = U8VecBitInput::with_bit_len(bytes.clone(), bit_len);
+        let mut first = ChecksummedBitInput::new(&mut input, 1000).unwrap();
+        assert_eq!(first.algorithm(), ChecksumAlgorithm::Crc32);
+        assert_eq!(first.read_i32().unwrap(), -123456);
+        assert_eq!(first.read_string(10).unwrap().unwrap(), "checked");
+        assert!(first.read_bool().unwrap());
+        first.read_bool().unwrap_err();
+        let mut second = ChecksummedBitInput::new(&mut input, 1000).unwrap();
+        assert_eq!(second.algorithm(), ChecksumAlgorithm::Adler32);
+        assert_eq!(second.read_var_u64().unwrap(), 98765);
+        second.read_bool().unwrap_err();
+
+        // Flipping any bit of the data of the first section must be detected
+        for bit_index in 8..first.bit_len() {
+            let mut corrupted = bytes.clone();
+            corrupted[bit_index / 8] ^= 1 << (bit_index % 8);
+            let mut input = U8VecBitInput::with_bit_len(corrupted, bit_len);
+            match ChecksummedBitInput::new(&mut input, 1000) {
+                Err(BitInputError::ChecksumMismatch(error)) => {
+                    assert_ne!(error.stored_checksum(), error.computed_checksum());
+                }
+                Err(other) => panic!("Expected ChecksumMismatchError, but got {:?}", other),
+                Ok(_) => panic!("The corruption of bit {} wasn't detected", bit_index),
+            }
+        }
+
+        let mut input = U8VecBitInput::with_bit_len(bytes, bit_len);
+        match ChecksummedBitInput::new(&mut input, 10) {
+            Err(BitInputError::Length(_)) => {}
+            Err(other) => panic!("Expected LengthError, but got {:?}", other),
+            Ok(_) => panic!("The section should have been too long"),
+        }
+    }
+
+    #[test]
+    fn test_hamming_bit_io() {
+        let mut output = HammingBitOutput::new(U8VecBitOutput::with_capacity(10));
+        output.add_i32(-987654);
+        output.add_string(Some("radio"));
+        output.add_bools_from_slice(&[true, false, true]);
+        let data_bits = output.bit_len();
+        let mut output = output.finish();
+        output.add_bool(true);
+        let (bytes, bit_len) = output.into_bytes_with_bit_len();
+
+        let check = |bytes: Vec<u8>, expected_corrections: usize| {
+            let mut input = U8VecBitInput::with_bit_len(bytes, bit_len);
+            let mut hamming = HammingBitInput::new(&mut input, 1000).unwrap();
+            assert_eq!(hamming.corrected_errors(), expected_corrections);
+            assert_eq!(hamming.read_i32().unwrap(), -987654);
+            assert_eq!(hamming.read_string(10).unwrap().unwrap(), "radio");
+            assert_eq!(hamming.read_bools(3).unwrap(), vec![true, false, true]);
+            hamming.read_bool().unwrap_err();
+            assert!(input.read_bool().unwrap());
+        };
+        check(bytes.clone(), 0);
+
+        // Every codeword is exactly 1 byte of the output, so flip 1 bit in every byte
+        let flip_bits = |byte: &mut u8, bit_indices: &[usize]| {
+            let mut bools = i8_to_bool_array(*byte as i8);
+            for bit_index in bit_indices {
+                bools[*bit_index] = !bools[*bit_index];
+            }
+            *byte = bool_array_to_i8(bools) as u8;
+        };
+        let codeword_count = bit_len / 8;
+        assert_eq!(codeword_count, (data_bits + 13).div_ceil(4));
+        let mut corrupted = bytes.clone();
+        for (codeword_index, byte) in corrupted.iter_mut().take(codeword_count).enumerate() {
+            flip_bits(byte, &[codeword_index % 8]);
+        }
+        check(corrupted, codeword_count);
+
+        // 2 flipped bits in the same codeword can be detected, but not corrected
+        let mut corrupted = bytes;
+        flip_bits(&mut corrupted[codeword_count / 2], &[2, 6]);
+        let mut input = U8VecBitInput::with_bit_len(corrupted, bit_len);
+        match HammingBitInput::new(&mut input, 1000) {
+            Err(BitInputError::Uncorrectable(_)) => {}
+            Err(other) => panic!("Expected UncorrectableError, but got {:?}", other),
+            Ok(_) => panic!("The double error wasn't detected"),
+        }
+    }
+
+    #[test]
+    fn test_reed_solomon() {
+        let codec = ReedSolomon::new(10);
+        assert_eq!(codec.max_data_bytes(), 245);
+        let mut seed = 1234;
+        let data: Vec<u8> = (0..1000).map(|_| next_random(&mut seed) as u8).collect();
+        let encoded = codec.encode(&data);
+        assert_eq!(encoded.len(), 1000 + 5 * 10);
+        assert_eq!(codec.decode(&encoded).unwrap(), (data.clone(), 0));
+
+        // A burst of 5 wrong bytes in every block can be corrected
+        let mut corrupted = encoded.clone();
+        for block_start in (0..encoded.len()).step_by(255) {
+            let burst_start = block_start + (next_random(&mut seed) % 20) as usize;
+            for byte in &mut corrupted[burst_start..burst_start + 5] {
+                *byte ^= 0xA5;
+            }
+        }
+        assert_eq!(codec.decode(&corrupted).unwrap(), (data.clone(), 25));
+
+        // The errors can also be scattered over the block
+        for error_count in 0..=5 {
+            let mut block = codec.encode_block(&data[..100]);
+            let original = block.clone();
+            for error_index in 0..error_count {
+                block[error_index * 21 + 3] ^= (error_index + 1) as u8;
+            }
+            assert_eq!(codec.decode_block(&mut block).unwrap(), error_count);
+            assert_eq!(block, original);
+        }
+
+        // 6 errors in a block is too much
+        let mut block = codec.encode_block(&data[..100]);
+        for byte in &mut block[50..56] {
+            *byte ^= 0xFF;
+        }
+        let unchanged = block.clone();
+        assert_eq!(codec.decode_block(&mut block), Err(UncorrectableError));
+        assert_eq!(block, unchanged);
+        match codec.decode(&[1, 2, 3]).unwrap_err() {
+            BitInputError::Uncorrectable(_) => {}
+            other => panic!("Expected UncorrectableError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_container() {
+        let mut writer = ContainerWriter::new(b"SAVE", 3, 0b101);
+        writer.add_string(Some("player"));
+        writer.add_var_u64(12345);
+        writer.add_bool(true);
+        let payload_bits = writer.bit_len();
+        let bytes = writer.finish();
+        assert!(bytes.starts_with(b"SAVE"));
+
+        let reader = ContainerReader::open(bytes.clone(), b"SAVE").unwrap();
+        assert_eq!(reader.version(), 3);
+        assert_eq!(reader.flags(), 0b101);
+        assert_eq!(reader.payload_bit_len(), payload_bits);
+        let mut payload = reader.payload();
+        assert_eq!(payload.read_string(10).unwrap().unwrap(), "player");
+        assert_eq!(payload.read_var_u64().unwrap(), 12345);
+        assert!(payload.read_bool().unwrap());
+        payload.read_bool().unwrap_err();
+
+        let empty = ContainerReader::open(ContainerWriter::new(b"", 0, 0).finish(), b"").unwrap();
+        assert_eq!(empty.version(), 0);
+        assert_eq!(empty.payload_bit_len(), 0);
+
+        match ContainerReader::open(bytes.clone(), b"LOAD") {
+            Err(BitInputError::InvalidMagic(_)) => {}
+            Err(other) => panic!("Expected InvalidMagicError, but got {:?}", other),
+            Ok(_) => panic!("The magic bytes should have been rejected"),
+        }
+        for index in 4..bytes.len() {
+            let mut corrupted = bytes.clone();
+            corrupted[index] ^= 0x10;
+            match ContainerReader::open(corrupted, b"SAVE") {
+                Err(BitInputError::ChecksumMismatch(_)) => {}
+                Err(other) => panic!("Expected ChecksumMismatchError, but got {:?}", other),
+                Ok(_) => panic!("The corruption of byte {} wasn't detected", index),
+            }
+        }
+        match ContainerReader::open(bytes[..6].to_vec(), b"SAVE") {
+            Err(BitInputError::InvalidCode(_)) => {}
+            Err(other) => panic!("Expected InvalidCodeError, but got {:?}", other),
+            Ok(_) => panic!("The truncated container should have been rejected"),
+        }
+    }
+
+    #[test]
+    fn test_schema() {
+        let item_schema = Schema::Struct(vec![
+            ("id".to_string(), Schema::Sized(12)),
+            ("name".to_string(), Schema::String(20)),
+        ]);
+        let schema = Schema::Struct(vec![
+            ("alive".to_string(), Schema::Bool),
+            ("health".to_string(), Schema::I16),
+            ("offset".to_string(), Schema::SignedSized(5)),
+            ("score".to_string(), Schema::VarU64),
+            ("scores".to_string(), Schema::I32Vec(5)),
+            ("guild".to_string(), Schema::Option(Box::new(Schema::String(20)))),
+            ("items".to_string(), Schema::List(Box::new(item_schema), 10)),
+        ]);
+        let item = |id: u64, name: &str| {
+            Value::Struct(vec![
+                ("id".to_string(), Value::U64(id)),
+                ("name".to_string(), Value::String(name.to_string())),
+            ])
+        };
+        let mut value = Value::Struct(vec![
+            ("alive".to_string(), Value::Bool(true)),
+            ("health".to_string(), Value::I16(-20)),
+            ("offset".to_string(), Value::I64(-16)),
+            ("score".to_string(), Value::U64(123456)),
+            ("scores".to_string(), Value::I32Vec(vec![5, -6, 7])),
+            ("guild".to_string(), Value::Option(None)),
+            ("items".to_string(), Value::List(vec![item(4095, "sword"), item(0, "")])),
+        ]);
+
+        // The schema must map to the normal add methods
+        let mut expected = BoolVecBitOutput::new(100);
+        expected.add_bool(true);
+        expected.add_i16(-20);
+        expected.add_sized_i64(-16, 5);
+        expected.add_var_u64(123456);
+        expected.add_i32_slice(&[5, -6, 7]);
+        expected.add_bool(false);
+        expected.add_var_u64(2);
+        expected.add_sized_u64(4095, 12);
+        expected.add_rust_string("sword");
+        expected.add_sized_u64(0, 12);
+        expected.add_rust_string("");
+        let mut output = BoolVecBitOutput::new(100);
+        encode_with_schema(&mut output, &schema, &value).unwrap();
+        assert_eq!(output.get_slice(), expected.get_slice());
+
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(decode_with_schema(&mut input, &schema).unwrap(), value);
+        input.read_bool().unwrap_err();
+
+        if let Value::Struct(fields) = &mut value {
+            fields[5].1 = Value::Option(Some(Box::new(Value::String("knights".to_string()))));
+            fields[6].1 = Value::List(vec![item(1, "shield"), item(4096, "too big")]);
+        }
+        let mut output = BoolVecBitOutput::new(100);
+        let error = encode_with_schema(&mut output, &schema, &value).unwrap_err();
+        assert_eq!(error.path(), ".items[1].id");
+        assert!(output.get_slice().is_empty());
+        assert!(Schema::SignedSized(5).validate(&Value::I64(16)).is_err());
+        assert!(Schema::U8.validate(&Value::U16(1)).is_err());
+
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_var_u64(11);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match decode_with_schema(&mut input, &Schema::List(Box::new(Schema::Bool), 10)) {
+            Err(BitInputError::Length(_)) => {}
+            other => panic!("Expected LengthError, but got {:?}", other),
+        }
+
+        // The lengths of I32Vec and Bytes are limited as well
+        assert!(Schema::Bytes(3).validate(&Value::Bytes(vec![1, 2, 3])).is_ok());
+        assert!(Schema::Bytes(3).validate(&Value::Bytes(vec![1, 2, 3, 4])).is_err());
+        assert!(Schema::I32Vec(1).validate(&Value::I32Vec(vec![1, 2])).is_err());
+        let mut output = BoolVecBitOutput::new(100);
+        encode_with_schema(&mut output, &Schema::Bytes(3), &Value::Bytes(vec![4, 5])).unwrap();
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(decode_with_schema(&mut input, &Schema::Bytes(3)), Ok(Value::Bytes(vec![4, 5])));
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_i32(-1);
+        output.add_u32(u32::MAX);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        for schema in &[Schema::I32Vec(10), Schema::Bytes(10)] {
+            match decode_with_schema(&mut input, schema) {
+                Err(BitInputError::Length(error)) => {
+                    assert_eq!(error.read_length(), u32::MAX as u64)
+                }
+                other => panic!("Expected LengthError, but got {:?}", other),
+            }
+        }
+
+        // Sized nodes can have at most 64 bits
+        assert!(Schema::Sized(64).validate(&Value::U64(u64::MAX)).is_ok());
+        assert!(Schema::SignedSized(64).validate(&Value::I64(i64::MIN)).is_ok());
+        assert!(Schema::Sized(70).validate(&Value::U64(5)).is_err());
+        assert!(Schema::SignedSized(70).validate(&Value::I64(-5)).is_err());
+        let mut output = BoolVecBitOutput::new(100);
+        encode_with_schema(&mut output, &Schema::Sized(70), &Value::U64(5)).unwrap_err();
+        assert!(output.get_slice().is_empty());
+        let bools = [false; 100];
+        for schema in &[Schema::Sized(70), Schema::SignedSized(70)] {
+            match decode_with_schema(&mut BoolSliceBitInput::new(&bools), schema) {
+                Err(BitInputError::InvalidCode(_)) => {}
+                other => panic!("Expected InvalidCodeError, but got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_codegen() {
+        let source = "
+            // The items in the inventory of a player
+            message Item {
+                id: i32;
+                name: string(50);
+            }
+
+            message Player {
+                alive: bool;
+                letter: char;
+                weapon: option<Item>;
+                scores: list<option<i64>>(10);
+                items: list<Item>(100);
+            }
+        ";
+        let schema = parse_schema(source).unwrap();
+        assert_eq!(2, schema.messages.len());
+        assert_eq!("Player", schema.messages[1].name);
+        assert_eq!(
+            FieldType::List(Box::new(FieldType::Option(Box::new(FieldType::I64))), 10),
+            schema.messages[1].fields[3].field_type
+        );
+
+        let rust = schema.generate_rust("bit_helper");
+        assert!(rust.contains("use bit_helper::input::*;"));
+        assert!(rust.contains("pub struct Player {"));
+        assert!(rust.contains("pub weapon: Option<Item>,"));
+        assert!(rust.contains("pub scores: Vec<Option<i64>>,"));
+        assert!(rust.contains("output.add_u16(self.letter);"));
+        assert!(rust.contains("let field_name = input.read_string(50)?;"));
+        assert!(rust.contains("let element_0 = Item::read(input)?;"));
+        assert!(rust.contains("LengthError::new(length_0 as u64, 100)"));
+
+        let java = schema.generate_java("com.example", "Messages");
+        assert!(java.contains("package com.example;"));
+        assert!(java.contains("public final class Messages {"));
+        assert!(java.contains("public static class Player {"));
+        assert!(java.contains("public List<Long> scores;"));
+        assert!(java.contains("output.addChar(this.letter);"));
+        assert!(java.contains("for (Item element_0 : this.items) {"));
+        assert!(java.contains("String field_name = input.readString();"));
+        assert!(java.contains("Item element_3 = Item.read(input);"));
+        assert!(java.contains("int length_1 = input.readInt();"));
+
+        let error = |source: &str| parse_schema(source).unwrap_err();
+        assert_eq!(3, error("message A {\n x: i32\n}").line());
+        assert_eq!(1, error("message A { x: B; }").line());
+        assert_eq!(1, error("message A { x: i32; x: i8; }").line());
+        assert_eq!(1, error("message A { type: i32; }").line());
+        for name in &["where", "unsafe", "yield", "char", "void", "null", "public", "throws", "_"] {
+            let error = error(&format!("message A {{\n {}: i32;\n}}", name));
+            assert_eq!(2, error.line());
+            assert_eq!(format!("{} is a reserved name", name), error.message());
+        }
+        for name in &["Self", "List", "BitInput", "Option", "while"] {
+            assert_eq!(2, error(&format!("\nmessage {} {{ x: i32; }}", name)).line());
+        }
+        assert_eq!(1, error("message A { x: option<option<i8>>; }").line());
+        assert_eq!(1, error("message A { x: option<string(5)>; }").line());
+        assert_eq!(1, error("message A { x: string(3000000000); }").line());
+        assert_eq!(2, error("message A {}\nmessage A {}").line());
+        assert_eq!(1, error("message A { b: B; }\nmessage B { a: option<A>; }").line());
+        assert!(parse_schema("message A { children: list<A>(5); }").is_ok());
+        assert_eq!(3, error("message A {\n\n x: i32; $").line());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_codegen_outer_class() {
+        let schema = parse_schema("message Messages { x: i32; }").unwrap();
+        schema.generate_java("", "Messages");
+    }
+
+    mod generated {
+        include!("test_messages.rs");
+    }
+
+    #[test]
+    fn test_generated_code() {
+        use generated::*;
+
+        // The committed code must be exactly what the code generator creates for the schema
+        let schema = parse_schema(include_str!("test_messages.schema")).unwrap();
+        assert_eq!(include_str!("test_messages.rs"), schema.generate_rust("crate"));
+        let java = schema.generate_java("", "TestMessages");
+        assert_eq!(include_str!("TestMessages.java"), java);
+
+        // Java doesn't allow 2 local variables with the same name in a method
+        let mut locals = Vec::new();
+        for line in java.lines().map(str::trim) {
+            if line.starts_with("public ") && line.ends_with(") {") {
+                locals.clear();
+            }
+            let line = line.strip_prefix("for (").unwrap_or(line);
+            let declaration = line.split(" = ").next().unwrap();
+            let words: Vec<_> = declaration.split_whitespace().collect();
+            if line.contains(" = ") && words.len() == 2 {
+                assert!(!locals.contains(&words[1]), "{} is declared twice", words[1]);
+                locals.push(words[1]);
+            }
+        }
+
+        let item = |id: i32, name: Option<&str>| Item {
+            id,
+            name: name.map(String::from),
+        };
+        let child = Player {
+            alive: false,
+            small: -128,
+            medium: 1234,
+            letter: 'x' as u16,
+            big: i64::MIN,
+            guild: None,
+            weapon: None,
+            scores: Vec::new(),
+            items: Vec::new(),
+            nested: Vec::new(),
+            children: Vec::new(),
+        };
+        let mut player = Player {
+            alive: true,
+            small: 12,
+            medium: -300,
+            letter: 0x20AC,
+            big: 1 << 40,
+            guild: Some("knights".to_string()),
+            weapon: Some(item(5, Some("sword"))),
+            scores: vec![Some(-1), None, Some(i64::MAX)],
+            items: vec![item(1, None), item(-2, Some("shield"))],
+            nested: vec![vec![1, 2, 3], Vec::new(), vec![-4]],
+            children: vec![child.clone(), child],
+        };
+        let mut output = BoolVecBitOutput::new(0);
+        player.write(&mut output);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(Ok(player.clone()), Player::read(&mut input));
+        input.read_bool().unwrap_err();
+
+        // The maximum lengths of the lists must be checked while reading
+        player.nested.push(Vec::new());
+        let mut output = BoolVecBitOutput::new(0);
+        player.write(&mut output);
+        match Player::read(&mut BoolSliceBitInput::new(output.get_slice())) {
+            Err(BitInputError::Length(error)) => assert_eq!(LengthError::new(4, 3), error),
+            other => panic!("Expected LengthError, but got {:?}", other),
+        }
+
+        // The temporary variables must not clash with the fields
+        let names = Names {
+            items: vec![1, 2],
+            items_length: 3,
+            items_index: 4,
+            items_element: vec![None, Some(5)],
+            weapon: Some(6),
+            weapon_value: 7,
+            length_0: 8,
+            value_0: None,
+        };
+        let mut output = BoolVecBitOutput::new(0);
+        names.write(&mut output);
+        assert_eq!(Ok(names), Names::read(&mut BoolSliceBitInput::new(output.get_slice())));
+    }
+
+    #[test]
+    fn test_versioned_records() {
+        // Version 1 of the player only has a name and a level
+        #[derive(Debug, PartialEq)]
+        struct PlayerV1 {
+            name: String,
+            level: i32,
+        }
+
+        impl PlayerV1 {
+            fn write(&self, output: &mut dyn BitOutput) {
+                let mut record = RecordWriter::new(1);
+                record.field(1, |output| output.add_rust_string(&self.name));
+                record.field(1, |output| output.add_i32(self.level));
+                record.write_to(output);
+            }
+
+            fn read(input: &mut dyn BitInput) -> Result<PlayerV1, BitInputError> {
+                let mut record = RecordReader::read(input, 1000)?;
+                Ok(PlayerV1 {
+                    name: record.field(1, String::new(), |input| input.read_rust_string(20))?,
+                    level: record.field(1, 0, |input| input.read_i32())?,
+                })
+            }
+        }
+
+        // Version 2 added an optional guild and a score with default value 100
+        #[derive(Debug, PartialEq)]
+        struct PlayerV2 {
+            name: String,
+            level: i32,
+            guild: Option<String>,
+            score: i32,
+        }
+
+        impl PlayerV2 {
+            fn write(&self, output: &mut dyn BitOutput, version: u64) {
+                let mut record = RecordWriter::new(version);
+                record.field(1, |output| output.add_rust_string(&self.name));
+                record.field(1, |output| output.add_i32(self.level));
+                record.optional_field(2, self.guild.as_ref(), |output, guild| {
+                    output.add_rust_string(guild)
+                });
+                record.field(2, |output| output.add_i32(self.score));
+                record.write_to(output);
+            }
+
+            fn read(input: &mut dyn BitInput) -> Result<PlayerV2, BitInputError> {
+                let mut record = RecordReader::read(input, 1000)?;
+                Ok(PlayerV2 {
+                    name: record.field(1, String::new(), |input| input.read_rust_string(20))?,
+                    level: record.field(1, 0, |input| input.read_i32())?,
+                    guild: record.optional_field(2, |input| input.read_rust_string(20))?,
+                    score: record.field(2, 100, |input| input.read_i32())?,
+                })
+            }
+        }
+
+        // Old payloads should be readable by the new type, using the defaults for the new fields
+        let old = PlayerV1 {
+            name: "knokko".to_string(),
+            level: 12,
+        };
+        let mut output = BoolVecBitOutput::new(0);
+        old.write(&mut output);
+        output.add_i8(-5);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        let expected = PlayerV2 {
+            name: "knokko".to_string(),
+            level: 12,
+            guild: None,
+            score: 100,
+        };
+        assert_eq!(expected, PlayerV2::read(&mut input).unwrap());
+        assert_eq!(-5, input.read_i8().unwrap());
+
+        // New payloads should be readable by the old type, which should skip the new fields
+        let new = PlayerV2 {
+            name: "builder".to_string(),
+            level: 3,
+            guild: Some("miners".to_string()),
+            score: 1234,
+        };
+        let mut output = BoolVecBitOutput::new(0);
+        new.write(&mut output, 2);
+        new.write(&mut output, 1);
+        output.add_i8(-5);
+        let expected = PlayerV1 {
+            name: "builder".to_string(),
+            level: 3,
+        };
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(expected, PlayerV1::read(&mut input).unwrap());
+        assert_eq!(expected, PlayerV1::read(&mut input).unwrap());
+        assert_eq!(-5, input.read_i8().unwrap());
+
+        // Writing with version 1 should leave out the new fields, even for new readers
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(new, PlayerV2::read(&mut input).unwrap());
+        let mut record = RecordReader::read(&mut input, 1000).unwrap();
+        assert_eq!(1, record.version());
+        assert_eq!(4, record.field_count());
+        let name = record.field(1, String::new(), |input| input.read_rust_string(20));
+        assert_eq!(Ok("builder".to_string()), name);
+        assert_eq!(Ok(3), record.field(1, 0, |input| input.read_i32()));
+        assert_eq!(Ok(None), record.optional_field(2, |input| input.read_rust_string(20)));
+        assert_eq!(Ok(100), record.field(2, 100, |input| input.read_i32()));
+
+        // The amount of data should be limited by max_bits
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match RecordReader::read(&mut input, 100) {
+            Err(BitInputError::Length(_)) => {}
+            Err(other) => panic!("Expected LengthError, but got {:?}", other),
+            Ok(_) => panic!("Expected LengthError, but got Ok"),
+        }
+    }
+
+    #[test]
+    fn test_add_string_types() {
+        let owned = Some(String::from("ab"));
+        let borrowed: Option<&String> = owned.as_ref();
+
+        let mut expected = BoolVecBitOutput::new(0);
+        expected.add_string(Some("ab"));
+        expected.add_string(None::<&str>);
+        expected.add_string(None::<&str>);
+
+        let mut output = BoolVecBitOutput::new(0);
+        output.add_string(borrowed);
+        output.add_string(None::<String>);
+        output.add_str(None);
+        assert_eq!(expected.get_vec(), output.get_vec());
+
+        let mut output = BoolVecBitOutput::new(0);
+        output.add_string(owned.clone());
+        let dyn_output: &mut dyn BitOutput = &mut output;
+        dyn_output.add_str(None);
+        dyn_output.add_string(None::<&String>);
+        assert_eq!(expected.get_vec(), output.get_vec());
+    }
+
+    #[test]
+    #[should_panic(expected = "The quotient of the Rice code must be at most")]
+    fn test_rice_quotient_too_large() {
+        let mut output = BoolVecBitOutput::new(100);
+        output.add_rice(u64::MAX, 0);
+    }
+
+    #[test]
+    fn test_rice_quotient_limit() {
+        let mut output = BoolVecBitOutput::new(0);
+        output.add_rice(MAX_RICE_QUOTIENT << 3 | 5, 3);
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        assert_eq!(Ok(MAX_RICE_QUOTIENT << 3 | 5), input.read_rice(3));
+
+        // A longer unary quotient must be rejected instead of read
+        let mut bools = vec![false; MAX_RICE_QUOTIENT as usize + 1];
+        bools.push(true);
+        match BoolSliceBitInput::new(&bools).read_rice(0) {
+            Err(BitInputError::InvalidCode(_)) => {}
+            other => panic!("Expected InvalidCodeError, but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_length_error() {
+        let table = HuffmanTable::english();
+        let mut output = BoolVecBitOutput::new(0);
+        output.add_huffman_bytes(b"bytes", table);
+        output.add_huffman_string("string", table);
+
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        match input.read_huffman_bytes(table, 4) {
+            Err(BitInputError::Length(error)) => {
+                assert_eq!(5, error.read_length());
+                assert_eq!(4, error.max_length());
+            }
+            other => panic!("Expected LengthError, but got {:?}", other),
+        }
+
+        let mut input = BoolSliceBitInput::new(output.get_slice());
+        input.read_huffman_bytes(table, 5).unwrap();
+        match input.read_huffman_string(table, 5) {
+            Err(BitInputError::StringLength(_)) => {}
+            other => panic!("Expected StringLengthError, but got {:?}", other),
+        }
+    }
 }
@@ -1,4 +1,9 @@
+use crate::alphabet::*;
 use crate::converter::*;
+use crate::huffman::*;
+use crate::input::*;
+use crate::packed::*;
+use crate::rank_select::*;
 
 /**
  * Instances of BitOutput can be used to save data to for the purpose to load the data later.
@@ -53,6 +58,19 @@ pub trait BitOutput {
      */
     fn terminate(&mut self);
 
+    /**
+     * Returns the exact number of bits that have been added to this BitOutput so far. Byte based
+     * implementations round their data up to whole bytes, so this can be used to find out how many bits
+     * of the last byte are meaningful and how many are just padding.
+     *
+     * Note that this method was added after the first version of this trait, and that it has no default
+     * implementation because only the implementation itself knows how many bits it holds. So this is a
+     * breaking change: implementations of BitOutput outside this crate need to implement it as well. An
+     * implementation that wraps another BitOutput can normally just return the bit_len of the wrapped output
+     * (plus or minus the bits it adds or buffers itself).
+     */
+    fn bit_len(&self) -> usize;
+
     /**
      * Add the provided u8 to this BitOutput without checking the capacity of this BitOutput. The
      * mirror function of this function is read_u8.
@@ -1577,6 +1595,531 @@ pub trait BitOutput {
         }
     }
 
+    /**
+     * Stores the given positive integer with the Elias gamma code. If the value is in the interval
+     * [2^n, 2^(n + 1) - 1], this takes 2n + 1 bits: n zeros, a one and the n least significant bits of the
+     * value. This is very compact for values that are almost always small.
+     *
+     * The value must be at least 1. If it is 0, this function will panic. Use add_exp_golomb with k = 0 if
+     * you also need to store 0.
+     *
+     * The mirror function of this function is read_elias_gamma.
+     */
+    fn add_elias_gamma(&mut self, value: u64) {
+        if value == 0 {
+            panic!("The Elias gamma code can't store 0");
+        }
+        let bits = 63 - value.leading_zeros() as usize;
+        self.ensure_extra_capacity(2 * bits + 1);
+        add_direct_unary(self, bits as u64);
+        self.add_direct_sized_u64(value & low_bits_mask(bits), bits);
+    }
+
+    /**
+     * Stores the given positive integer with the Elias delta code. If the value is in the interval
+     * [2^n, 2^(n + 1) - 1], the number n + 1 is stored with the Elias gamma code, followed by the n least
+     * significant bits of the value. This takes less bits than the Elias gamma code for larger values.
+     *
+     * The value must be at least 1. If it is 0, this function will panic.
+     *
+     * The mirror function of this function is read_elias_delta.
+     */
+    fn add_elias_delta(&mut self, value: u64) {
+        if value == 0 {
+            panic!("The Elias delta code can't store 0");
+        }
+        let bits = 63 - value.leading_zeros() as usize;
+        self.add_elias_gamma(bits as u64 + 1);
+        self.add_sized_u64(value & low_bits_mask(bits), bits);
+    }
+
+    /**
+     * Stores the given integer with the exponential Golomb code of order k. This is equivalent to storing
+     * value + 2^k with the Elias gamma code, but without the first k zeros. Values smaller than 2^k take
+     * k + 1 bits and every doubling of the value takes 2 more bits. Unlike the Elias codes, this code can
+     * store 0.
+     *
+     * The order k must be at most 64. If it is larger, this function will panic.
+     *
+     * The mirror function of this function is read_exp_golomb.
+     */
+    fn add_exp_golomb(&mut self, value: u64, k: usize) {
+        if k > 64 {
+            panic!(
+                "The order of the exponential Golomb code must be at most 64, but it is {}",
+                k
+            );
+        }
+        let shifted = value as u128 + (1u128 << k);
+        let bits = 127 - shifted.leading_zeros() as usize;
+        self.ensure_extra_capacity(2 * bits + 1 - k);
+        add_direct_unary(self, (bits - k) as u64);
+        self.add_direct_sized_u64((shifted & low_bits_mask(bits) as u128) as u64, bits);
+    }
+
+    /**
+     * Stores the given integer with the Golomb-Rice code with parameter k: the quotient value / 2^k is stored
+     * in unary (that many zeros followed by a one) and the remainder is stored with k bits. This is optimal
+     * for geometrically distributed values with a mean around 2^k.
+     *
+     * Because the quotient is stored in unary, this code should only be used when value / 2^k is small. The
+     * parameter k must be at most 64 and the quotient must be at most MAX_RICE_QUOTIENT. If either of them is
+     * larger, this function will panic before anything is written.
+     *
+     * The mirror function of this function is read_rice.
+     */
+    fn add_rice(&mut self, value: u64, k: usize) {
+        if k > 64 {
+            panic!(
+                "The parameter of the Rice code must be at most 64, but it is {}",
+                k
+            );
+        }
+        let quotient = if k == 64 { 0 } else { value >> k };
+        if quotient > MAX_RICE_QUOTIENT {
+            panic!(
+                "The quotient of the Rice code must be at most {}, but {} / 2^{} is {}",
+                MAX_RICE_QUOTIENT, value, k, quotient
+            );
+        }
+        self.ensure_extra_capacity(quotient as usize + 1 + k);
+        add_direct_unary(self, quotient);
+        self.add_direct_sized_u64(value & low_bits_mask(k), k);
+    }
+
+    /**
+     * Stores a sorted sequence of u64s compactly: the length and the first value are stored with add_var_u64
+     * and all other values are stored as the gap (difference) with their predecessor. The given codec
+     * determines how the gaps are stored. This is much more compact than storing all values with 64 bits when
+     * the values are close to each other, for instance sorted ids or timestamps.
+     *
+     * The values must be sorted in ascending order (duplicates are allowed). If they are not sorted, this
+     * function will panic before anything is written.
+     *
+     * The mirror function of this function is read_sorted_u64_seq.
+     */
+    fn add_sorted_u64_seq(&mut self, values: &[u64], codec: GapCodec) {
+        if let Some(index) = values.windows(2).position(|pair| pair[1] < pair[0]) {
+            panic!(
+                "The values must be sorted, but {} comes after {}",
+                values[index + 1],
+                values[index]
+            );
+        }
+        self.add_var_u64(values.len() as u64);
+        if values.is_empty() {
+            return;
+        }
+        self.add_var_u64(values[0]);
+        if values.len() == 1 {
+            return;
+        }
+        let gaps = values.windows(2).map(|pair| pair[1] - pair[0]);
+        match codec {
+            GapCodec::VarU64 => {
+                self.add_bool(false);
+                for gap in gaps {
+                    self.add_var_u64(gap);
+                }
+            }
+            GapCodec::Rice => {
+                let k = choose_rice_parameter(values);
+                self.add_bool(true);
+                self.add_sized_u64(k as u64, 6);
+                for gap in gaps {
+                    self.add_rice(gap, k);
+                }
+            }
+        }
+    }
+
+    /**
+     * Stores the given i64s compactly with frame-of-reference packing: the length and the smallest value are
+     * stored first, followed by the difference between every value and the smallest value. All differences are
+     * stored with the same number of bits, which is the number of bits that is needed for the largest
+     * difference. This is very compact when all values are close to each other, but a single value that is far
+     * away from the others will make every value take more bits. Use add_patched_i64_slice in that case.
+     *
+     * The mirror function of this function is read_packed_i64_vec.
+     */
+    fn add_packed_i64_slice(&mut self, values: &[i64]) {
+        self.add_var_u64(values.len() as u64);
+        if let Some(min) = values.iter().min() {
+            self.add_i64(*min);
+            let offsets: Vec<u64> = values
+                .iter()
+                .map(|value| value.wrapping_sub(*min) as u64)
+                .collect();
+            add_packed_offsets(self, &offsets, false);
+        }
+    }
+
+    /**
+     * Stores the given u32s compactly with frame-of-reference packing. This works the same way as
+     * add_packed_i64_slice.
+     *
+     * The mirror function of this function is read_packed_u32_vec.
+     */
+    fn add_packed_u32_slice(&mut self, values: &[u32]) {
+        self.add_var_u64(values.len() as u64);
+        if let Some(min) = values.iter().min() {
+            self.add_u32(*min);
+            let offsets: Vec<u64> = values.iter().map(|value| (value - min) as u64).collect();
+            add_packed_offsets(self, &offsets, false);
+        }
+    }
+
+    /**
+     * Stores the given i64s with patched frame-of-reference packing (PFOR). This is like add_packed_i64_slice,
+     * but the number of bits per value is chosen such that the total size is minimized. The values whose
+     * difference with the smallest value doesn't fit in that number of bits are exceptions: their index and
+     * their remaining bits are stored separately after the other values. So a few huge values will only make
+     * themselves more expensive instead of all values.
+     *
+     * The mirror function of this function is read_patched_i64_vec.
+     */
+    fn add_patched_i64_slice(&mut self, values: &[i64]) {
+        self.add_var_u64(values.len() as u64);
+        if let Some(min) = values.iter().min() {
+            self.add_i64(*min);
+            let offsets: Vec<u64> = values
+                .iter()
+                .map(|value| value.wrapping_sub(*min) as u64)
+                .collect();
+            add_packed_offsets(self, &offsets, true);
+        }
+    }
+
+    /**
+     * Stores the given u32s with patched frame-of-reference packing (PFOR). This works the same way as
+     * add_patched_i64_slice.
+     *
+     * The mirror function of this function is read_patched_u32_vec.
+     */
+    fn add_patched_u32_slice(&mut self, values: &[u32]) {
+        self.add_var_u64(values.len() as u64);
+        if let Some(min) = values.iter().min() {
+            self.add_u32(*min);
+            let offsets: Vec<u64> = values.iter().map(|value| (value - min) as u64).collect();
+            add_packed_offsets(self, &offsets, true);
+        }
+    }
+
+    /**
+     * Stores the given bools with run-length encoding: the length and the first bool are stored first, followed
+     * by the length of every run of equal bools (stored with add_var_u64). Since consecutive runs always have
+     * different values, the values of the other runs don't need to be stored. This is very compact for bools
+     * that consist of long runs, but very expensive for bools that change often. Use add_auto_rle_bools if you
+     * don't know which kind of bools you will get.
+     *
+     * The mirror function of this function is read_rle_bools.
+     */
+    fn add_rle_bools(&mut self, bools: &[bool]) {
+        self.add_var_u64(bools.len() as u64);
+        if let Some(first) = bools.first() {
+            self.add_bool(*first);
+            for_each_run(bools, |_, run_length| {
+                self.add_var_u64(run_length as u64 - 1)
+            });
+        }
+    }
+
+    /**
+     * Stores the given u32s with run-length encoding: the length is stored first, followed by the value and the
+     * length of every run of equal values (both stored with add_var_u64).
+     *
+     * The mirror function of this function is read_rle_u32_vec.
+     */
+    fn add_rle_u32_slice(&mut self, values: &[u32]) {
+        self.add_var_u64(values.len() as u64);
+        for_each_run(values, |value, run_length| {
+            self.add_var_u64(value as u64);
+            self.add_var_u64(run_length as u64 - 1);
+        });
+    }
+
+    /**
+     * Stores the given bools either with add_rle_bools or as 1 bit per bool (after their length), whichever
+     * takes less bits. One extra bit is used to store which of the two was chosen.
+     *
+     * The mirror function of this function is read_auto_rle_bools.
+     */
+    fn add_auto_rle_bools(&mut self, bools: &[bool]) {
+        let mut rle_bits = 1;
+        for_each_run(bools, |_, run_length| {
+            rle_bits += var_u64_bits(run_length as u64 - 1)
+        });
+        if rle_bits < bools.len() {
+            self.add_bool(true);
+            self.add_rle_bools(bools);
+        } else {
+            self.add_bool(false);
+            self.add_var_u64(bools.len() as u64);
+            self.add_bools_from_slice(bools);
+        }
+    }
+
+    /**
+     * Stores the given u32s either with add_rle_u32_slice or as 32 bits per value (after their length),
+     * whichever takes less bits. One extra bit is used to store which of the two was chosen.
+     *
+     * The mirror function of this function is read_auto_rle_u32_vec.
+     */
+    fn add_auto_rle_u32_slice(&mut self, values: &[u32]) {
+        let mut rle_bits = 0;
+        for_each_run(values, |value, run_length| {
+            rle_bits += var_u64_bits(value as u64) + var_u64_bits(run_length as u64 - 1)
+        });
+        if rle_bits < 32 * values.len() {
+            self.add_bool(true);
+            self.add_rle_u32_slice(values);
+        } else {
+            self.add_bool(false);
+            self.add_var_u64(values.len() as u64);
+            self.add_u32s_from_slice(values);
+        }
+    }
+
+    /**
+     * Stores the given bools as the indices of the bools that are true, or as the indices of the bools that are
+     * false when there are less false bools than true bools. The length and a bool that indicates which of the
+     * two was chosen are stored first, followed by the indices (stored with add_sorted_u64_seq using the Rice
+     * gap codec). This is very compact for large bool slices where almost all bools have the same value.
+     *
+     * The mirror function of this function is read_sparse_bools.
+     */
+    fn add_sparse_bools(&mut self, bools: &[bool]) {
+        self.add_var_u64(bools.len() as u64);
+        if bools.is_empty() {
+            return;
+        }
+        let true_count = bools.iter().filter(|value| **value).count();
+        let polarity = true_count <= bools.len() - true_count;
+        let indices: Vec<u64> = bools
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value == polarity)
+            .map(|(index, _)| index as u64)
+            .collect();
+        self.add_bool(polarity);
+        self.add_sorted_u64_seq(&indices, GapCodec::Rice);
+    }
+
+    /**
+     * Stores the given PackedIntVec. The length and the bit width of the vector are stored first, followed by
+     * all its integers. The integers are stored in the same way as add_sized_u64 would store them, but they are
+     * copied word by word instead of one by one.
+     *
+     * The mirror function of this function is read_packed_int_vec.
+     */
+    fn add_packed_int_vec(&mut self, vec: &PackedIntVec) {
+        vec.add_to(self);
+    }
+
+    /**
+     * Stores the given RankSelectBitVec. Only the length and the bits are stored, so this takes 1 bit per bit of
+     * the vector (plus the length). The auxiliary index of the vector will be rebuilt by the reader.
+     *
+     * The mirror function of this function is read_rank_select_bit_vec.
+     */
+    fn add_rank_select_bit_vec(&mut self, vec: &RankSelectBitVec) {
+        vec.add_to(self);
+    }
+
+    /**
+     * Reads the next amount bits from the given bit input and adds them to this bit output. This can be used to
+     * copy a part of the data of a bit input without knowing its structure. If the bit input doesn't have amount
+     * bits left, an InputCapacityError will be returned and nothing will be read or added.
+     */
+    fn add_bits_from_input(
+        &mut self,
+        input: &mut dyn BitInput,
+        amount: usize,
+    ) -> Result<(), BitInputError> {
+        input.ensure_extra_capacity(amount)?;
+        self.ensure_extra_capacity(amount);
+        for _ in 0..amount / 8 {
+            self.add_direct_i8(input.read_direct_i8());
+        }
+        for _ in 0..amount % 8 {
+            self.add_direct_bool(input.read_direct_bool());
+        }
+        Ok(())
+    }
+
+    /**
+     * Does exactly the same as add_string (see BitOutputExt), but only accepts an Option<&str>.
+     *
+     * The mirror function of this function is read_string.
+     */
+    fn add_str(&mut self, value: Option<&str>) {
+        let string = match value {
+            Some(string) => string,
+            None => {
+                self.add_i8(0);
+                return;
+            }
+        };
+
+        // The java variant stores the UTF-16 code units of the string, so this one must do the same
+        let mut length = 0;
+        let mut min = u16::MAX;
+        let mut max = 0;
+        for unit in string.encode_utf16() {
+            length += 1;
+            min = min.min(unit);
+            max = max.max(unit);
+        }
+
+        if length < 254 {
+            self.ensure_extra_capacity(8);
+            self.add_direct_i8((length + 1) as i8);
+        } else {
+            self.ensure_extra_capacity(40);
+            self.add_direct_i8(-1);
+            self.add_direct_i32(length as i32);
+        }
+
+        if length > 0 {
+            let difference = max - min;
+            let bit_count = get_required_bits(difference as u64) as usize;
+
+            self.ensure_extra_capacity(21 + bit_count * length);
+            self.add_direct_u16(min);
+            self.add_direct_sized_u64(bit_count as u64, 5);
+
+            if bit_count > 0 {
+                for unit in string.encode_utf16() {
+                    self.add_direct_sized_u64((unit - min) as u64, bit_count);
+                }
+            }
+        }
+    }
+
+    /**
+     * Adds a string to this bit output using the native Rust encoding: the number of UTF-8 bytes
+     * is stored with add_var_u64 and is followed by the UTF-8 bytes of the string. This is usually
+     * more compact than add_string for non-ASCII text and it is faster because the bytes can be copied
+     * directly when this bit output happens to be byte-aligned.
+     *
+     * Unlike add_string, this function is NOT compatible with the java and javascript variants of
+     * BitHelper and it can't store None.
+     *
+     * The mirror function of this function is read_rust_string.
+     */
+    fn add_rust_string(&mut self, value: &str) {
+        let bytes = value.as_bytes();
+        self.add_var_u64(bytes.len() as u64);
+        self.add_u8s_from_slice(bytes);
+    }
+
+    /**
+     * Adds a string that consists only of ASCII characters to this bit output. The number of characters
+     * is stored with add_var_u64 and every character will only take 7 bits.
+     *
+     * If the given string contains a character that is not ASCII, this function will panic.
+     *
+     * The mirror function of this function is read_ascii_string.
+     */
+    fn add_ascii_string(&mut self, value: &str) {
+        if !value.is_ascii() {
+            panic!("The string {:?} contains non-ASCII characters", value);
+        }
+        let bytes = value.as_bytes();
+        self.add_var_u64(bytes.len() as u64);
+        self.ensure_extra_capacity(7 * bytes.len());
+        for byte in bytes {
+            self.add_direct_sized_u64(*byte as u64, 7);
+        }
+    }
+
+    /**
+     * Adds a string that only consists of characters of the given alphabet to this bit output. The number
+     * of characters is stored with add_var_u64 and every character is stored as its index in the alphabet,
+     * which takes at most ceil(log2(n)) bits when the alphabet has n characters.
+     *
+     * If the string contains a character that is not part of the alphabet, a CharNotInAlphabetError will
+     * be returned and nothing will be added to this bit output.
+     *
+     * The mirror function of this function is read_alphabet_string.
+     */
+    fn add_alphabet_string(
+        &mut self,
+        value: &str,
+        alphabet: &Alphabet,
+    ) -> Result<(), CharNotInAlphabetError> {
+        let mut indices = Vec::with_capacity(value.len());
+        for character in value.chars() {
+            match alphabet.index_of(character) {
+                Some(index) => indices.push(index),
+                None => return Err(CharNotInAlphabetError::new(character)),
+            }
+        }
+
+        self.add_var_u64(indices.len() as u64);
+        self.ensure_extra_capacity(indices.len() * alphabet.max_bits_per_char());
+        for index in indices {
+            alphabet.add_direct_index(self, index);
+        }
+        Ok(())
+    }
+
+    /**
+     * Adds the given Huffman table to this bit output. The code length of every byte value is stored with
+     * 5 bits, so this takes 160 bytes. This is useful when the table was trained for the data that follows it.
+     *
+     * The mirror function of this function is read_huffman_table.
+     */
+    fn add_huffman_table(&mut self, table: &HuffmanTable) {
+        self.ensure_extra_capacity(5 * HUFFMAN_SYMBOLS);
+        for length in table.lengths().iter() {
+            self.add_direct_sized_u64(*length as u64, 5);
+        }
+    }
+
+    /**
+     * Adds the given bytes to this bit output using the given Huffman table. The number of bytes is stored
+     * with add_var_u64 and is followed by the Huffman code of every byte.
+     *
+     * If one of the bytes doesn't have a code in the table, this function will panic.
+     *
+     * The mirror function of this function is read_huffman_bytes.
+     */
+    fn add_huffman_bytes(&mut self, bytes: &[u8], table: &HuffmanTable) {
+        let bits = match table.encoded_bits(bytes) {
+            Some(bits) => bits,
+            None => panic!(
+                "Not all bytes of {:?} have a code in the Huffman table",
+                bytes
+            ),
+        };
+        self.add_var_u64(bytes.len() as u64);
+        self.ensure_extra_capacity(bits);
+        for byte in bytes {
+            table.add_direct_symbol(self, *byte);
+        }
+    }
+
+    /**
+     * Adds the given string to this bit output using the given Huffman table. The UTF-8 bytes of the string
+     * are stored with add_huffman_bytes, so the default table for English text will store ASCII text quite
+     * compactly.
+     *
+     * If one of the UTF-8 bytes doesn't have a code in the table, this function will panic.
+     *
+     * The mirror function of this function is read_huffman_string.
+     */
+    fn add_huffman_string(&mut self, value: &str, table: &HuffmanTable) {
+        self.add_huffman_bytes(value.as_bytes(), table);
+    }
+}
+
+/**
+ * Contains the methods of BitOutput that have a type parameter. They can't be part of BitOutput
+ * itself, because then it could no longer be used as dyn BitOutput. This trait is implemented
+ * for every BitOutput, including dyn BitOutput.
+ */
+pub trait BitOutputExt: BitOutput {
     /**
      * Adds a string option to this bit output. This method uses a string option instead of just
      * a string and uses a quite weird encoding to make this method compatible with the java and
@@ -1587,55 +2130,184 @@ pub trait BitOutput {
      * When some string is passed, the read_string of the corresponding input will return a Some
      * containing an equivalent string as the one passed to this method.
      *
+     * Any string type can be passed, for instance an Option<&str>, Option<String> or
+     * Option<&String>. Strings of any length are supported. Because of the type parameter, a
+     * None needs a type, like None::<&str>.
+     *
      * If you don't care about compatibility with java and javascript, you can use add_rust_string
      * instead.
      *
      * The mirror function of this function is read_string.
      */
-    fn add_string(&mut self, value: Option<&String>) {
-        if value.is_none() {
-            self.add_i8(0);
-        } else {
-            self.ensure_extra_capacity(29);
+    fn add_string<S: AsRef<str>>(&mut self, value: Option<S>) {
+        self.add_str(value.as_ref().map(|string| string.as_ref()));
+    }
+}
 
-            let string = value.unwrap();
+impl<O: BitOutput + ?Sized> BitOutputExt for O {}
 
-            let length = string.encode_utf16().count();
-            if length < 254 {
-                self.add_direct_i8((length + 1) as i8);
-            } else {
-                self.ensure_extra_capacity(32);
-                self.add_direct_i8(-1);
-                self.add_direct_i32(length as i32);
-            }
+/**
+ * The largest quotient that add_rice can store. Since the quotient is stored in unary, larger quotients would
+ * take an unreasonable amount of memory. read_rice will return an InvalidCodeError when it encounters a larger
+ * quotient, so corrupted data can't make it read (and store) an unreasonable amount of bits.
+ */
+pub const MAX_RICE_QUOTIENT: u64 = 1 << 20;
 
-            if string.len() > 0 {
-                let min = string.encode_utf16().min().unwrap();
-                let max = string.encode_utf16().max().unwrap();
+/**
+ * The ways in which the gaps between the values of a sorted sequence can be stored by the add_sorted_u64_seq
+ * method of BitOutput. The chosen codec is stored in the output, so the reader doesn't need to know it.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GapCodec {
+    /**
+     * Store every gap with add_var_u64. This works reasonably well for any sequence, but every gap takes at
+     * least 7 bits.
+     */
+    VarU64,
+    /**
+     * Store every gap with add_rice, using the parameter that results in the smallest output for the given
+     * sequence. This is usually the best choice when the gaps are small or evenly spread.
+     */
+    Rice,
+}
 
-                let difference = max - min;
-                let bit_count;
-                if difference == 0 {
-                    bit_count = 0;
-                } else {
-                    bit_count = get_required_bits(difference as u64) as usize;
-                }
+/**
+ * Chooses the Rice parameter that stores the gaps of the given sorted values with the least bits. The best
+ * parameter is always close to the binary logarithm of the average gap, so only its neighbours are tried.
+ */
+fn choose_rice_parameter(values: &[u64]) -> usize {
+    let gap_count = values.len() as u64 - 1;
+    let average_gap = (values[values.len() - 1] - values[0]) / gap_count;
+    let estimate = 63 - average_gap.max(1).leading_zeros() as usize;
+    let cost = |k: usize| -> u128 {
+        values
+            .windows(2)
+            .map(|pair| ((pair[1] - pair[0]) >> k) as u128 + 1 + k as u128)
+            .sum()
+    };
+    let mut best_k = estimate;
+    let mut best_cost = cost(estimate);
+    for k in [estimate.saturating_sub(1), (estimate + 1).min(63)].iter() {
+        let candidate_cost = cost(*k);
+        if candidate_cost < best_cost {
+            best_k = *k;
+            best_cost = candidate_cost;
+        }
+    }
 
-                self.add_direct_u16(min);
-                self.add_direct_sized_u64(bit_count as u64, 5);
-
-                if difference > 0 {
-                    self.ensure_extra_capacity(bit_count * length);
-                    let mut iterator = string.encode_utf16();
-                    let mut maybe_next = iterator.next();
-                    while maybe_next.is_some() {
-                        let next = maybe_next.unwrap();
-                        self.add_direct_sized_u64((next - min) as u64, bit_count);
-                        maybe_next = iterator.next();
-                    }
-                }
-            }
+    // A single large gap could have a quotient that is too large for add_rice
+    let max_gap = values.windows(2).map(|pair| pair[1] - pair[0]).max().unwrap_or(0);
+    while max_gap >> best_k > MAX_RICE_QUOTIENT {
+        best_k += 1;
+    }
+    best_k
+}
+
+/**
+ * Adds the bit width and the given offsets to the output. If patched is true, a smaller bit width may be used
+ * for most offsets and the offsets that don't fit in it will be stored as exceptions after the other offsets.
+ * This is used by the methods that store packed integer slices.
+ */
+fn add_packed_offsets<O: BitOutput + ?Sized>(output: &mut O, offsets: &[u64], patched: bool) {
+    let max_width = offsets
+        .iter()
+        .map(|offset| get_required_bits(*offset) as usize)
+        .max()
+        .unwrap_or(0);
+    output.add_sized_u64(max_width as u64, 7);
+    if !patched {
+        output.ensure_extra_capacity(offsets.len() * max_width);
+        for offset in offsets {
+            output.add_direct_sized_u64(*offset, max_width);
         }
+        return;
+    }
+
+    let width = choose_patched_width(offsets, max_width);
+    output.add_sized_u64(width as u64, 7);
+    output.ensure_extra_capacity(offsets.len() * width);
+    let mask = low_bits_mask(width);
+    let mut exceptions = Vec::new();
+    for (index, offset) in offsets.iter().enumerate() {
+        output.add_direct_sized_u64(offset & mask, width);
+        if *offset > mask {
+            exceptions.push(index as u64);
+        }
+    }
+    output.add_sorted_u64_seq(&exceptions, GapCodec::Rice);
+    let high_width = max_width - width;
+    output.ensure_extra_capacity(exceptions.len() * high_width);
+    for index in exceptions {
+        output.add_direct_sized_u64(offsets[index as usize] >> width, high_width);
+    }
+}
+
+/**
+ * Chooses the bit width that minimizes the (estimated) size of the given offsets when they are stored with
+ * patched frame-of-reference packing. Every offset costs width bits and every exception additionally costs
+ * its remaining bits and its index.
+ */
+fn choose_patched_width(offsets: &[u64], max_width: usize) -> usize {
+    let mut histogram = [0usize; 65];
+    for offset in offsets {
+        histogram[get_required_bits(*offset) as usize] += 1;
+    }
+    // A rough estimate of the number of bits that is needed to store the index of an exception
+    let index_bits = get_required_bits(offsets.len() as u64) as usize + 1;
+
+    let mut best_width = max_width;
+    let mut best_cost = offsets.len() * max_width;
+    let mut exceptions = 0;
+    for width in (0..max_width).rev() {
+        exceptions += histogram[width + 1];
+        let cost = offsets.len() * width + exceptions * (max_width - width + index_bits);
+        if cost < best_cost {
+            best_width = width;
+            best_cost = cost;
+        }
+    }
+    best_width
+}
+
+/**
+ * Calls the given function for every run of equal values in the given slice, in order. The first parameter
+ * is the value of the run and the second parameter is its length.
+ */
+fn for_each_run<T: PartialEq + Copy, F: FnMut(T, usize)>(values: &[T], mut function: F) {
+    let mut run_start = 0;
+    for index in 1..=values.len() {
+        if index == values.len() || values[index] != values[run_start] {
+            function(values[run_start], index - run_start);
+            run_start = index;
+        }
+    }
+}
+
+/**
+ * Returns the number of bits add_var_u64 needs to store the given value.
+ */
+fn var_u64_bits(value: u64) -> usize {
+    6 + (get_required_bits(value) as usize).max(1)
+}
+
+/**
+ * Adds the given amount of zeros followed by a one to the output, without checking its capacity.
+ */
+fn add_direct_unary<O: BitOutput + ?Sized>(output: &mut O, amount: u64) {
+    for _ in 0..amount {
+        output.add_direct_bool(false);
+    }
+    output.add_direct_bool(true);
+}
+
+/**
+ * Returns the u64 whose least significant 'bits' bits are 1 and whose other bits are 0.
+ */
+pub(crate) fn low_bits_mask(bits: usize) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
     }
 }
 
@@ -1652,6 +2324,65 @@ fn get_required_bits(number: u64) -> u8 {
     power
 }
 
+/**
+ * Appends the first other_bit_len bits of the bytes of other to the first bit_len bits of the bytes of vector
+ * and returns the new bit length. The to_u8 and from_u8 functions convert the elements of the vectors to and
+ * from u8s. This is used to implement the append_output methods of the byte outputs.
+ */
+fn append_bits<T: Copy>(
+    vector: &mut Vec<T>,
+    bit_len: usize,
+    other: &[T],
+    other_bit_len: usize,
+    to_u8: fn(T) -> u8,
+    from_u8: fn(u8) -> T,
+) -> usize {
+    let other = &other[..other_bit_len.div_ceil(8)];
+    let shift = bit_len % 8;
+    if shift == 0 {
+        vector.truncate(bit_len / 8);
+        vector.extend_from_slice(other);
+    } else {
+        // The bits are shifted in their natural order, in which the unused bits of a byte are 0
+        let mut current = to_ordered_byte(to_u8(vector[bit_len / 8]));
+        vector.truncate(bit_len / 8);
+        vector.reserve(other.len() + 1);
+        for byte in other {
+            let ordered = to_ordered_byte(to_u8(*byte));
+            vector.push(from_u8(from_ordered_byte(current | (ordered >> shift))));
+            current = ordered << (8 - shift);
+        }
+        vector.push(from_u8(from_ordered_byte(current)));
+    }
+    let new_bit_len = bit_len + other_bit_len;
+    vector.truncate(new_bit_len.div_ceil(8));
+    new_bit_len
+}
+
+/**
+ * Converts a byte that was created with bool_array_to_i8 to the byte whose most significant bit is the first
+ * bool, whose second most significant bit is the second bool... This is the inverse of from_ordered_byte.
+ */
+fn to_ordered_byte(byte: u8) -> u8 {
+    if byte < 128 {
+        (byte << 1) | 1
+    } else {
+        !byte << 1
+    }
+}
+
+/**
+ * Converts a byte whose most significant bit is the first bool to the corresponding byte of bool_array_to_i8.
+ * This is the inverse of to_ordered_byte.
+ */
+fn from_ordered_byte(byte: u8) -> u8 {
+    if byte & 1 == 1 {
+        byte >> 1
+    } else {
+        !(byte >> 1)
+    }
+}
+
 /**
  * This is the most straight-forward implementation of BitOutput. It literally uses booleans to store
  * its data. Unfortunately, boolean vectors take a lot of memory, so this is usually not a compact
@@ -1677,6 +2408,10 @@ impl BitOutput for BoolVecBitOutput {
     fn terminate(&mut self) {
         self.vector.shrink_to_fit();
     }
+
+    fn bit_len(&self) -> usize {
+        self.vector.len()
+    }
 }
 
 impl BoolVecBitOutput {
@@ -1781,6 +2516,22 @@ impl BitOutput for I8VecBitOutput {
     fn terminate(&mut self) {
         self.vector.shrink_to_fit();
     }
+
+    fn bit_len(&self) -> usize {
+        8 * self.byte_index + self.bool_index
+    }
+
+    fn add_direct_u8s_from_slice(&mut self, u8s: &[u8]) {
+        if self.bool_index == 0 {
+            // The bytes can be copied directly when this bit output is byte-aligned
+            self.vector.extend(u8s.iter().map(|byte| *byte as i8));
+            self.byte_index += u8s.len();
+        } else {
+            for value in u8s {
+                self.add_direct_u8(*value);
+            }
+        }
+    }
 }
 
 impl I8VecBitOutput {
@@ -1805,6 +2556,36 @@ impl I8VecBitOutput {
     pub fn to_i8_vector(&self) -> Vec<i8> {
         self.vector.clone()
     }
+
+    /**
+     * Adds all bits of the other bit output to the end of this bit output, including the bits of its last
+     * (partial) byte. This is much faster than adding the bits one by one, even if this bit output is not
+     * byte-aligned: every byte of the other bit output will be shifted and copied as a whole.
+     */
+    pub fn append_output(&mut self, other: &I8VecBitOutput) {
+        let bit_len = self.bit_len();
+        let new_bit_len = append_bits(
+            &mut self.vector,
+            bit_len,
+            &other.vector,
+            other.bit_len(),
+            |byte| byte as u8,
+            |byte| byte as i8,
+        );
+        self.byte_index = new_bit_len / 8;
+        self.bool_index = new_bit_len % 8;
+    }
+
+    /**
+     * Consumes this bit output and returns its vector together with the exact number of bits that were added to
+     * it (see bit_len). The bits of the last i8 that are not part of the bit length are padding. The bit length
+     * can be given to I8VecBitInput::with_bit_len to prevent the bit input from reading the padding.
+     */
+    pub fn into_bytes_with_bit_len(mut self) -> (Vec<i8>, usize) {
+        let bit_len = self.bit_len();
+        self.vector.shrink_to_fit();
+        (self.vector, bit_len)
+    }
 }
 
 impl std::fmt::Debug for I8VecBitOutput {
@@ -1893,6 +2674,22 @@ impl BitOutput for U8VecBitOutput {
     fn terminate(&mut self) {
         self.vector.shrink_to_fit();
     }
+
+    fn bit_len(&self) -> usize {
+        8 * self.byte_index + self.bool_index
+    }
+
+    fn add_direct_u8s_from_slice(&mut self, u8s: &[u8]) {
+        if self.bool_index == 0 {
+            // The bytes can be copied directly when this bit output is byte-aligned
+            self.vector.extend_from_slice(u8s);
+            self.byte_index += u8s.len();
+        } else {
+            for value in u8s {
+                self.add_direct_u8(*value);
+            }
+        }
+    }
 }
 
 impl U8VecBitOutput {
@@ -1919,4 +2716,34 @@ impl U8VecBitOutput {
     pub fn to_u8_vector(&self) -> Vec<u8> {
         self.vector.clone()
     }
+
+    /**
+     * Adds all bits of the other bit output to the end of this bit output, including the bits of its last
+     * (partial) byte. This is much faster than adding the bits one by one, even if this bit output is not
+     * byte-aligned: every byte of the other bit output will be shifted and copied as a whole.
+     */
+    pub fn append_output(&mut self, other: &U8VecBitOutput) {
+        let bit_len = self.bit_len();
+        let new_bit_len = append_bits(
+            &mut self.vector,
+            bit_len,
+            &other.vector,
+            other.bit_len(),
+            |byte| byte,
+            |byte| byte,
+        );
+        self.byte_index = new_bit_len / 8;
+        self.bool_index = new_bit_len % 8;
+    }
+
+    /**
+     * Consumes this bit output and returns its vector together with the exact number of bits that were added to
+     * it (see bit_len). The bits of the last u8 that are not part of the bit length are padding. The bit length
+     * can be given to U8VecBitInput::with_bit_len to prevent the bit input from reading the padding.
+     */
+    pub fn into_bytes_with_bit_len(mut self) -> (Vec<u8>, usize) {
+        let bit_len = self.bit_len();
+        self.vector.shrink_to_fit();
+        (self.vector, bit_len)
+    }
 }
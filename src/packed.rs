@@ -0,0 +1,225 @@
+use crate::converter::*;
+use crate::input::*;
+use crate::output::*;
+
+/**
+ * A vector of unsigned integers that all take the same number of bits (the bit width). The integers are
+ * packed tightly in u64 words, so a vector of 1000 integers with a bit width of 5 takes only 5000 bits of
+ * memory. Every integer can still be read and changed in constant time with get and set.
+ *
+ * The integers are stored in the same order as add_sized_u64 of BitOutput would store them: the first bit
+ * of a word is its most significant bit. Thanks to that, a PackedIntVec can be stored in a BitOutput with
+ * add_packed_int_vec and read again with read_packed_int_vec of BitInput by copying whole words instead of
+ * encoding every integer separately.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackedIntVec {
+    words: Vec<u64>,
+    bits: usize,
+    len: usize,
+}
+
+impl PackedIntVec {
+    /**
+     * Creates a new empty vector for integers with the given bit width. The bit width must be at most 64.
+     */
+    pub fn new(bits: usize) -> PackedIntVec {
+        check_unsigned_bitcount(bits);
+        PackedIntVec {
+            words: Vec::new(),
+            bits,
+            len: 0,
+        }
+    }
+
+    /**
+     * Creates a new vector for integers with the given bit width that initially contains len zeros.
+     */
+    pub fn with_len(bits: usize, len: usize) -> PackedIntVec {
+        check_unsigned_bitcount(bits);
+        PackedIntVec {
+            words: vec![0; word_count(len * bits)],
+            bits,
+            len,
+        }
+    }
+
+    /**
+     * Creates a new vector for integers with the given bit width that contains the given values. This function
+     * will panic if one of the values doesn't fit in the given number of bits.
+     */
+    pub fn from_values(bits: usize, values: &[u64]) -> PackedIntVec {
+        let mut vec = PackedIntVec::with_len(bits, values.len());
+        for (index, value) in values.iter().enumerate() {
+            vec.set(index, *value);
+        }
+        vec
+    }
+
+    /**
+     * Returns the number of integers in this vector.
+     */
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /**
+     * Returns true if this vector doesn't contain any integers.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /**
+     * Returns the number of bits every integer of this vector takes.
+     */
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /**
+     * Returns the integer at the given index. This function will panic if the index is out of bounds.
+     */
+    pub fn get(&self, index: usize) -> u64 {
+        self.check_index(index);
+        let start = index * self.bits;
+        let word_index = start / 64;
+        let offset = start % 64;
+        if self.bits == 0 {
+            0
+        } else if offset + self.bits <= 64 {
+            (self.words[word_index] >> (64 - offset - self.bits)) & low_bits_mask(self.bits)
+        } else {
+            let high_bits = 64 - offset;
+            let low_bits = self.bits - high_bits;
+            let high = self.words[word_index] & low_bits_mask(high_bits);
+            (high << low_bits) | (self.words[word_index + 1] >> (64 - low_bits))
+        }
+    }
+
+    /**
+     * Changes the integer at the given index to the given value. This function will panic if the index is out
+     * of bounds or if the value doesn't fit in the bit width of this vector.
+     */
+    pub fn set(&mut self, index: usize, value: u64) {
+        self.check_index(index);
+        check_unsigned_overflow(value, self.bits);
+        let start = index * self.bits;
+        let word_index = start / 64;
+        let offset = start % 64;
+        if self.bits == 0 {
+            return;
+        }
+        if offset + self.bits <= 64 {
+            let shift = 64 - offset - self.bits;
+            let mask = low_bits_mask(self.bits) << shift;
+            self.words[word_index] = (self.words[word_index] & !mask) | (value << shift);
+        } else {
+            let high_bits = 64 - offset;
+            let low_bits = self.bits - high_bits;
+            let high_mask = low_bits_mask(high_bits);
+            self.words[word_index] = (self.words[word_index] & !high_mask) | (value >> low_bits);
+            let low_shift = 64 - low_bits;
+            let low_mask = low_bits_mask(low_bits) << low_shift;
+            self.words[word_index + 1] =
+                (self.words[word_index + 1] & !low_mask) | (value << low_shift);
+        }
+    }
+
+    /**
+     * Adds the given value to the end of this vector. This function will panic if the value doesn't fit in the
+     * bit width of this vector.
+     */
+    pub fn push(&mut self, value: u64) {
+        check_unsigned_overflow(value, self.bits);
+        self.len += 1;
+        let required_words = word_count(self.len * self.bits);
+        self.words.resize(required_words, 0);
+        self.set(self.len - 1, value);
+    }
+
+    /**
+     * Returns an iterator over the integers of this vector.
+     */
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        (0..self.len).map(move |index| self.get(index))
+    }
+
+    /**
+     * Copies all integers of this vector to a new Vec.
+     */
+    pub fn to_vec(&self) -> Vec<u64> {
+        self.iter().collect()
+    }
+
+    /**
+     * Adds the length, the bit width and all integers of this vector to the output.
+     */
+    pub(crate) fn add_to<O: BitOutput + ?Sized>(&self, output: &mut O) {
+        output.add_var_u64(self.len as u64);
+        output.add_sized_u64(self.bits as u64, 7);
+        add_words(output, &self.words, self.len * self.bits);
+    }
+
+    /**
+     * Reads a vector that was stored with add_to from the input. If the stored length is larger than
+     * max_length, a LengthError will be returned. If the stored bit width is larger than 64, an
+     * InvalidCodeError will be returned.
+     */
+    pub(crate) fn read_from<I: BitInput + ?Sized>(
+        input: &mut I,
+        max_length: usize,
+    ) -> Result<PackedIntVec, BitInputError> {
+        let len = read_length(input, max_length)?;
+        let bits = input.read_sized_u64(7)? as usize;
+        if bits > 64 {
+            return Err(BitInputError::InvalidCode(InvalidCodeError));
+        }
+        let words = read_words(input, len.saturating_mul(bits))?;
+        Ok(PackedIntVec { words, bits, len })
+    }
+
+    fn check_index(&self, index: usize) {
+        if index >= self.len {
+            panic!(
+                "The index is {}, but the length of the PackedIntVec is {}",
+                index, self.len
+            );
+        }
+    }
+}
+
+/**
+ * Returns the number of u64 words that is needed to store the given number of bits.
+ */
+pub(crate) fn word_count(bits: usize) -> usize {
+    bits.div_ceil(64)
+}
+
+/**
+ * Adds the first total_bits bits of the given words to the output, starting with the most significant bit of
+ * the first word. The remaining bits of the last word are not stored.
+ */
+pub(crate) fn add_words<O: BitOutput + ?Sized>(output: &mut O, words: &[u64], total_bits: usize) {
+    output.ensure_extra_capacity(total_bits);
+    for (word_index, word) in words.iter().enumerate() {
+        let word_bits = (total_bits - 64 * word_index).min(64);
+        output.add_direct_sized_u64(word >> (64 - word_bits), word_bits);
+    }
+}
+
+/**
+ * Reads total_bits bits that were stored with add_words. The remaining bits of the last word will be 0.
+ */
+pub(crate) fn read_words<I: BitInput + ?Sized>(
+    input: &mut I,
+    total_bits: usize,
+) -> Result<Vec<u64>, BitInputError> {
+    input.ensure_extra_capacity(total_bits)?;
+    let mut words = Vec::with_capacity(word_count(total_bits));
+    for word_index in 0..word_count(total_bits) {
+        let word_bits = (total_bits - 64 * word_index).min(64);
+        words.push(input.read_direct_sized_u64(word_bits) << (64 - word_bits));
+    }
+    Ok(words)
+}
@@ -0,0 +1,107 @@
+use crate::chunked::*;
+use crate::output::*;
+
+/**
+ * The position of a chunk in the output of encode_parallel_with_offsets: the index of the first item of the
+ * chunk and the bit offset at which the data of that item starts.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkOffset {
+    pub first_item: usize,
+    pub bit_offset: usize,
+}
+
+/**
+ * Encodes all given items with the given encode function, using thread_count threads. The items are split
+ * into thread_count contiguous chunks and every thread encodes one chunk into its own U8VecBitOutput. The
+ * outputs are joined with append_output afterwards, so the result is exactly the same as the result of
+ * calling the encode function for every item on a single U8VecBitOutput, in order. A thread_count of 0 is
+ * treated as 1.
+ *
+ * The encode function is called once for every item and must only write that item. It must not depend on
+ * the data that was written for other items, because the threads don't know what the others wrote.
+ */
+pub fn encode_parallel<T, F>(items: &[T], thread_count: usize, encode: F) -> U8VecBitOutput
+where
+    T: Sync,
+    F: Fn(&mut U8VecBitOutput, &T) + Sync,
+{
+    encode_parallel_with_offsets(items, thread_count, encode).0
+}
+
+/**
+ * Does the same as encode_parallel, but also returns the offset of every chunk in the output. A reader can use
+ * these offsets to decode the chunks independently, for instance in parallel or only the chunk that contains
+ * the item it needs. Use encode_parallel_chunked instead to store the offsets in the output itself.
+ */
+pub fn encode_parallel_with_offsets<T, F>(
+    items: &[T],
+    thread_count: usize,
+    encode: F,
+) -> (U8VecBitOutput, Vec<ChunkOffset>)
+where
+    T: Sync,
+    F: Fn(&mut U8VecBitOutput, &T) + Sync,
+{
+    let mut result = U8VecBitOutput::with_capacity(0);
+    let mut offsets = Vec::new();
+    if items.is_empty() {
+        return (result, offsets);
+    }
+    let chunk_size = items.len().div_ceil(thread_count.max(1));
+    let encode = &encode;
+    let chunk_outputs: Vec<U8VecBitOutput> = std::thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut output = U8VecBitOutput::with_capacity(0);
+                    for item in chunk {
+                        encode(&mut output, item);
+                    }
+                    output
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                // Let the panic of an encode thread continue in the calling thread
+                handle
+                    .join()
+                    .unwrap_or_else(|error| std::panic::resume_unwind(error))
+            })
+            .collect()
+    });
+
+    let total_bytes: usize = chunk_outputs.iter().map(|output| output.vector.len()).sum();
+    result.ensure_extra_capacity(8 * total_bytes);
+    for (chunk_index, chunk_output) in chunk_outputs.iter().enumerate() {
+        offsets.push(ChunkOffset {
+            first_item: chunk_index * chunk_size,
+            bit_offset: result.bit_len(),
+        });
+        result.append_output(chunk_output);
+    }
+    (result, offsets)
+}
+
+/**
+ * Does the same as encode_parallel_with_offsets, but writes the chunk offsets into the output instead of
+ * returning them. The result is a container in the format of ChunkedWriter, where every chunk contains the
+ * items of 1 thread. So it can be read on its own with a ChunkedReader, which can decode any item without
+ * decoding the chunks before it, or decode all chunks in parallel with decode_all_parallel.
+ */
+pub fn encode_parallel_chunked<T, F>(items: &[T], thread_count: usize, encode: F) -> Vec<u8>
+where
+    T: Sync,
+    F: Fn(&mut U8VecBitOutput, &T) + Sync,
+{
+    let (output, offsets) = encode_parallel_with_offsets(items, thread_count, encode);
+    let items_per_chunk = items.len().div_ceil(thread_count.max(1)).max(1);
+    let chunk_offsets: Vec<u64> = offsets
+        .iter()
+        .map(|offset| offset.bit_offset as u64)
+        .collect();
+    finish_chunked(output, items_per_chunk, items.len(), &chunk_offsets)
+}
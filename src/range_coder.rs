@@ -0,0 +1,303 @@
+use crate::input::*;
+use crate::output::*;
+
+const PROBABILITY_BITS: u32 = 11;
+const PROBABILITY_ONE: u16 = 1 << PROBABILITY_BITS;
+const ADAPTATION_SHIFT: u32 = 5;
+const TOP_VALUE: u32 = 1 << 24;
+
+/**
+ * An adaptive probability model for bools that are encoded with a RangeEncoder. The model keeps track
+ * of the probability that the next bool is false and updates it after every bool that is encoded or
+ * decoded. Bools with a skewed probability (for instance bools that are almost always false) will
+ * therefore take only a fraction of a bit.
+ *
+ * The decoder must use a model that was created in the same way as the model of the encoder and the
+ * models must be used for the same bools in the same order.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptiveBitModel {
+    probability: u16,
+}
+
+impl AdaptiveBitModel {
+    /**
+     * Creates a new model that starts with a probability of 50% for both values.
+     */
+    pub fn new() -> AdaptiveBitModel {
+        AdaptiveBitModel {
+            probability: PROBABILITY_ONE / 2,
+        }
+    }
+
+    fn update(&mut self, value: bool) {
+        if value {
+            self.probability -= self.probability >> ADAPTATION_SHIFT;
+        } else {
+            self.probability += (PROBABILITY_ONE - self.probability) >> ADAPTATION_SHIFT;
+        }
+    }
+}
+
+impl Default for AdaptiveBitModel {
+    fn default() -> AdaptiveBitModel {
+        AdaptiveBitModel::new()
+    }
+}
+
+/**
+ * An adaptive probability model for symbols of a small alphabet: the symbols are the integers in the
+ * interval [0, symbol_count>. Every symbol is encoded as a path in a binary tree of AdaptiveBitModels,
+ * so symbols that occur often will take less bits than symbols that occur rarely.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdaptiveSymbolModel {
+    symbol_count: u32,
+    bits: u32,
+    nodes: Vec<AdaptiveBitModel>,
+}
+
+impl AdaptiveSymbolModel {
+    /**
+     * Creates a new model for the symbols in the interval [0, symbol_count>. All symbols start with the same
+     * probability. The symbol_count must be at least 1 and at most 2^16.
+     */
+    pub fn new(symbol_count: u32) -> AdaptiveSymbolModel {
+        if symbol_count == 0 || symbol_count > 1 << 16 {
+            panic!(
+                "The symbol count must be in the interval [1, 65536], but it is {}",
+                symbol_count
+            );
+        }
+        let mut bits = 0;
+        while (1 << bits) < symbol_count {
+            bits += 1;
+        }
+        AdaptiveSymbolModel {
+            symbol_count,
+            bits,
+            nodes: vec![AdaptiveBitModel::new(); 1 << bits],
+        }
+    }
+
+    /**
+     * Returns the number of symbols of this model.
+     */
+    pub fn symbol_count(&self) -> u32 {
+        self.symbol_count
+    }
+}
+
+/**
+ * A range coder (a kind of arithmetic coder) that writes its bytes to a BitOutput. It can encode bools and
+ * small symbols at close to their entropy by using adaptive probability models (AdaptiveBitModel and
+ * AdaptiveSymbolModel).
+ *
+ * The encoded data can be decoded with a RangeDecoder that reads from a BitInput that was created from the
+ * bit output. The decoder must use the same models in the same order as the encoder. The finish method must
+ * be called after the last value has been encoded. The decoder will read exactly the bytes that were written
+ * by the encoder, so the bit output can be used to store other data after the encoder has been finished.
+ */
+pub struct RangeEncoder<'a, O: BitOutput + ?Sized> {
+    output: &'a mut O,
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: u64,
+}
+
+impl<'a, O: BitOutput + ?Sized> RangeEncoder<'a, O> {
+    /**
+     * Creates a new range encoder that will write its bytes to the given bit output.
+     */
+    pub fn new(output: &'a mut O) -> RangeEncoder<'a, O> {
+        RangeEncoder {
+            output,
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+        }
+    }
+
+    /**
+     * Encodes the given bool with the given model and updates the model.
+     *
+     * The mirror function of this function is decode_bool of RangeDecoder.
+     */
+    pub fn encode_bool(&mut self, model: &mut AdaptiveBitModel, value: bool) {
+        let bound = (self.range >> PROBABILITY_BITS) * model.probability as u32;
+        if value {
+            self.low += bound as u64;
+            self.range -= bound;
+        } else {
+            self.range = bound;
+        }
+        model.update(value);
+        self.normalize();
+    }
+
+    /**
+     * Encodes the given symbol with the given model and updates the model. The symbol must be smaller than the
+     * symbol count of the model, or this function will panic.
+     *
+     * The mirror function of this function is decode_symbol of RangeDecoder.
+     */
+    pub fn encode_symbol(&mut self, model: &mut AdaptiveSymbolModel, symbol: u32) {
+        if symbol >= model.symbol_count {
+            panic!(
+                "The symbol {} is not smaller than the symbol count {}",
+                symbol, model.symbol_count
+            );
+        }
+        let mut node = 1;
+        for bit_index in (0..model.bits).rev() {
+            let value = (symbol >> bit_index) & 1 == 1;
+            self.encode_bool(&mut model.nodes[node], value);
+            node = (node << 1) | value as usize;
+        }
+    }
+
+    /**
+     * Encodes the given unsigned integer with the given amount of bits, without using a model. Every bit will
+     * take exactly 1 bit. This can be useful for values that are uniformly distributed. The number of bits
+     * can be at most 32 and the value must be smaller than 2^bits.
+     *
+     * The mirror function of this function is decode_direct_bits of RangeDecoder.
+     */
+    pub fn encode_direct_bits(&mut self, value: u32, bits: u32) {
+        for bit_index in (0..bits).rev() {
+            self.range >>= 1;
+            if (value >> bit_index) & 1 == 1 {
+                self.low += self.range as u64;
+            }
+            self.normalize();
+        }
+    }
+
+    /**
+     * Writes the remaining state of this encoder to the bit output. This must be called after the last value
+     * has been encoded, or the decoder won't be able to decode the last values.
+     */
+    pub fn finish(mut self) {
+        for _ in 0..5 {
+            self.shift_low();
+        }
+    }
+
+    fn normalize(&mut self) {
+        while self.range < TOP_VALUE {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    fn shift_low(&mut self) {
+        if self.low < 0xFF00_0000 || self.low > u32::MAX as u64 {
+            let carry = (self.low >> 32) as u8;
+            let mut pending = self.cache;
+            while self.cache_size > 0 {
+                self.output.add_u8(pending.wrapping_add(carry));
+                pending = 0xFF;
+                self.cache_size -= 1;
+            }
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+    }
+}
+
+/**
+ * The decoder of the data that was encoded by a RangeEncoder. It reads its bytes from a BitInput and it must
+ * use the same models in the same order as the encoder.
+ */
+pub struct RangeDecoder<'a, I: BitInput + ?Sized> {
+    input: &'a mut I,
+    code: u32,
+    range: u32,
+}
+
+impl<'a, I: BitInput + ?Sized> RangeDecoder<'a, I> {
+    /**
+     * Creates a new range decoder that will read from the given bit input. This will immediately read the
+     * first 5 bytes of the encoded data.
+     */
+    pub fn new(input: &'a mut I) -> Result<RangeDecoder<'a, I>, BitInputError> {
+        let mut code = 0;
+        for _ in 0..5 {
+            code = (code << 8) | input.read_u8()? as u32;
+        }
+        Ok(RangeDecoder {
+            input,
+            code,
+            range: u32::MAX,
+        })
+    }
+
+    /**
+     * Decodes a bool with the given model and updates the model.
+     *
+     * The mirror function of this function is encode_bool of RangeEncoder.
+     */
+    pub fn decode_bool(&mut self, model: &mut AdaptiveBitModel) -> Result<bool, BitInputError> {
+        let bound = (self.range >> PROBABILITY_BITS) * model.probability as u32;
+        let value = self.code >= bound;
+        if value {
+            self.code -= bound;
+            self.range -= bound;
+        } else {
+            self.range = bound;
+        }
+        model.update(value);
+        self.normalize()?;
+        Ok(value)
+    }
+
+    /**
+     * Decodes a symbol with the given model and updates the model. If the decoded symbol is not smaller than the
+     * symbol count of the model, an InvalidCodeError will be returned.
+     *
+     * The mirror function of this function is encode_symbol of RangeEncoder.
+     */
+    pub fn decode_symbol(&mut self, model: &mut AdaptiveSymbolModel) -> Result<u32, BitInputError> {
+        let mut node = 1;
+        for _ in 0..model.bits {
+            let value = self.decode_bool(&mut model.nodes[node])?;
+            node = (node << 1) | value as usize;
+        }
+        let symbol = (node - (1 << model.bits)) as u32;
+        if symbol < model.symbol_count {
+            Ok(symbol)
+        } else {
+            Err(BitInputError::InvalidCode(InvalidCodeError))
+        }
+    }
+
+    /**
+     * Decodes an unsigned integer that was encoded with the given amount of bits, without using a model.
+     *
+     * The mirror function of this function is encode_direct_bits of RangeEncoder.
+     */
+    pub fn decode_direct_bits(&mut self, bits: u32) -> Result<u32, BitInputError> {
+        let mut result = 0;
+        for _ in 0..bits {
+            self.range >>= 1;
+            let value = self.code >= self.range;
+            if value {
+                self.code -= self.range;
+            }
+            result = (result << 1) | value as u32;
+            self.normalize()?;
+        }
+        Ok(result)
+    }
+
+    fn normalize(&mut self) -> Result<(), BitInputError> {
+        while self.range < TOP_VALUE {
+            self.range <<= 8;
+            self.code = (self.code << 8) | self.input.read_u8()? as u32;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use crate::input::*;
+use crate::output::*;
+use crate::packed::*;
+
+// Every superblock consists of this many words and stores the number of ones before it
+const WORDS_PER_SUPERBLOCK: usize = 8;
+
+/**
+ * An immutable bit vector that can answer rank and select queries quickly. The rank of an index is the number
+ * of ones before that index and the select of k is the index of the k-th one. This is useful for large
+ * bitmaps that are used as an index: for instance, the rank of an index in an occupancy bitmap is the index
+ * of the corresponding element in a dense array that only contains the occupied elements.
+ *
+ * The bits are packed in u64 words. Besides the bits, an auxiliary index is kept that takes about 3/8 extra
+ * memory: the number of ones before every superblock of 512 bits and the number of ones before every word in
+ * its superblock. The rank1 method needs constant time and the select1 method needs logarithmic time.
+ *
+ * A RankSelectBitVec can be stored with add_rank_select_bit_vec of BitOutput and read again with
+ * read_rank_select_bit_vec of BitInput. Only the bits are stored; the reader will rebuild the index.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RankSelectBitVec {
+    words: Vec<u64>,
+    len: usize,
+    count_ones: usize,
+    superblock_ranks: Vec<u64>,
+    word_ranks: Vec<u16>,
+}
+
+impl RankSelectBitVec {
+    /**
+     * Creates a new RankSelectBitVec that contains the given bools.
+     */
+    pub fn from_bools(bools: &[bool]) -> RankSelectBitVec {
+        let mut words = vec![0; word_count(bools.len())];
+        for (index, value) in bools.iter().enumerate() {
+            if *value {
+                words[index / 64] |= 1 << (63 - index % 64);
+            }
+        }
+        RankSelectBitVec::from_words(words, bools.len())
+    }
+
+    /**
+     * Creates the RankSelectBitVec with the given words and builds its index. The bits of the last word that are
+     * not part of the vector must be 0.
+     */
+    fn from_words(words: Vec<u64>, len: usize) -> RankSelectBitVec {
+        let mut superblock_ranks = Vec::with_capacity(words.len() / WORDS_PER_SUPERBLOCK + 1);
+        let mut word_ranks = Vec::with_capacity(words.len());
+        let mut count_ones = 0;
+        let mut superblock_ones = 0;
+        for (word_index, word) in words.iter().enumerate() {
+            if word_index % WORDS_PER_SUPERBLOCK == 0 {
+                superblock_ranks.push(count_ones as u64);
+                superblock_ones = 0;
+            }
+            word_ranks.push(superblock_ones);
+            count_ones += word.count_ones() as usize;
+            superblock_ones += word.count_ones() as u16;
+        }
+        RankSelectBitVec {
+            words,
+            len,
+            count_ones,
+            superblock_ranks,
+            word_ranks,
+        }
+    }
+
+    /**
+     * Returns the number of bits of this vector.
+     */
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /**
+     * Returns true if this vector doesn't have any bits.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /**
+     * Returns the number of ones in this vector.
+     */
+    pub fn count_ones(&self) -> usize {
+        self.count_ones
+    }
+
+    /**
+     * Returns the bit at the given index. This function will panic if the index is out of bounds.
+     */
+    pub fn get(&self, index: usize) -> bool {
+        if index >= self.len {
+            panic!(
+                "The index is {}, but the length of the RankSelectBitVec is {}",
+                index, self.len
+            );
+        }
+        (self.words[index / 64] >> (63 - index % 64)) & 1 == 1
+    }
+
+    /**
+     * Returns the number of ones before the given index (the bit at the index itself is not counted). The index
+     * can be at most the length of this vector. This function needs constant time.
+     */
+    pub fn rank1(&self, index: usize) -> usize {
+        if index >= self.len {
+            if index > self.len {
+                panic!(
+                    "The index is {}, but the length of the RankSelectBitVec is {}",
+                    index, self.len
+                );
+            }
+            return self.count_ones;
+        }
+        let word_index = index / 64;
+        let bits_before = index % 64;
+        let mask = if bits_before == 0 {
+            0
+        } else {
+            u64::MAX << (64 - bits_before)
+        };
+        self.superblock_ranks[word_index / WORDS_PER_SUPERBLOCK] as usize
+            + self.word_ranks[word_index] as usize
+            + (self.words[word_index] & mask).count_ones() as usize
+    }
+
+    /**
+     * Returns the number of zeros before the given index. The index can be at most the length of this vector.
+     */
+    pub fn rank0(&self, index: usize) -> usize {
+        index - self.rank1(index)
+    }
+
+    /**
+     * Returns the index of the k-th one of this vector (the first one has k = 0), or None if this vector has at
+     * most k ones. This function uses a binary search over the superblocks and then scans at most 1 superblock.
+     */
+    pub fn select1(&self, k: usize) -> Option<usize> {
+        if k >= self.count_ones {
+            return None;
+        }
+        let superblock = self
+            .superblock_ranks
+            .partition_point(|rank| *rank as usize <= k)
+            - 1;
+        let mut remaining = k - self.superblock_ranks[superblock] as usize;
+        let first_word = superblock * WORDS_PER_SUPERBLOCK;
+        let bound_word = (first_word + WORDS_PER_SUPERBLOCK).min(self.words.len());
+        for word_index in first_word..bound_word {
+            let mut word = self.words[word_index];
+            let ones = word.count_ones() as usize;
+            if remaining < ones {
+                for _ in 0..remaining {
+                    // Clear the most significant one
+                    word &= !(1 << (63 - word.leading_zeros()));
+                }
+                return Some(64 * word_index + word.leading_zeros() as usize);
+            }
+            remaining -= ones;
+        }
+        unreachable!("The superblock ranks should be consistent with the words")
+    }
+
+    /**
+     * Adds the length and the bits of this vector to the output.
+     */
+    pub(crate) fn add_to<O: BitOutput + ?Sized>(&self, output: &mut O) {
+        output.add_var_u64(self.len as u64);
+        add_words(output, &self.words, self.len);
+    }
+
+    /**
+     * Reads a vector that was stored with add_to from the input and rebuilds its index. If the stored length is
+     * larger than max_length, a LengthError will be returned.
+     */
+    pub(crate) fn read_from<I: BitInput + ?Sized>(
+        input: &mut I,
+        max_length: usize,
+    ) -> Result<RankSelectBitVec, BitInputError> {
+        let len = read_length(input, max_length)?;
+        let words = read_words(input, len)?;
+        Ok(RankSelectBitVec::from_words(words, len))
+    }
+}
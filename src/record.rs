@@ -0,0 +1,189 @@
+use crate::bit_vec::*;
+use crate::input::*;
+use crate::output::*;
+
+/**
+ * Writes a versioned record: a group of fields that can still be read after fields have been added to it (by
+ * readers that don't know the new fields yet) and that can be read by readers that expect fields that didn't
+ * exist yet when the record was written. Every field is annotated with the version of the record in which it
+ * was added. The record is written to a BitOutput by write_to, and consists of:
+ * - the version of the record (stored with add_var_u64)
+ * - the number of fields (stored with add_var_u64)
+ * - the presence bitmap: 1 bool per field that tells whether the field is present
+ * - for every present field: the number of bits of its data (stored with add_var_u64), followed by its data
+ *
+ * Because every field stores its own length, readers can skip the fields they don't know. The fields must be
+ * written in the order in which they were added to the record, and they must be read in the same order by a
+ * RecordReader.
+ */
+pub struct RecordWriter {
+    version: u64,
+    fields: Vec<Option<BitVec>>,
+}
+
+impl RecordWriter {
+    /**
+     * Creates a new RecordWriter for a record with the given version. This is normally the latest version, but
+     * an older version can be used to write records for older readers: fields that were added after the given
+     * version will simply be absent.
+     */
+    pub fn new(version: u64) -> RecordWriter {
+        RecordWriter {
+            version,
+            fields: Vec::new(),
+        }
+    }
+
+    /**
+     * Adds the next field, which was added in since_version, to this record. The data of the field is written
+     * by the write function. If since_version is larger than the version of this record, the field will be
+     * absent and the write function won't be called.
+     */
+    pub fn field<F: FnOnce(&mut dyn BitOutput)>(&mut self, since_version: u64, write: F) {
+        self.optional_field(since_version, Some(()), |output, _| write(output));
+    }
+
+    /**
+     * Adds the next field, which was added in since_version, to this record. If the value is None (or if
+     * since_version is larger than the version of this record), the field will be absent. Otherwise, the value
+     * is written by the write function.
+     */
+    pub fn optional_field<T, F: FnOnce(&mut dyn BitOutput, T)>(
+        &mut self,
+        since_version: u64,
+        value: Option<T>,
+        write: F,
+    ) {
+        let data = match value {
+            Some(value) if since_version <= self.version => {
+                let mut data = BitVec::new();
+                write(&mut data, value);
+                Some(data)
+            }
+            _ => None,
+        };
+        self.fields.push(data);
+    }
+
+    /**
+     * Returns the version of this record.
+     */
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /**
+     * Writes this record to the given output.
+     */
+    pub fn write_to(&self, output: &mut dyn BitOutput) {
+        output.add_var_u64(self.version);
+        output.add_var_u64(self.fields.len() as u64);
+        for field in &self.fields {
+            output.add_bool(field.is_some());
+        }
+        for data in self.fields.iter().flatten() {
+            output.add_var_u64(data.len() as u64);
+            output
+                .add_bits_from_input(&mut BitVecBitInput::new(data), data.len())
+                .expect("The data input should have exactly enough bits");
+        }
+    }
+}
+
+/**
+ * Reads a record that was written by a RecordWriter. The fields must be read in the same order as they were
+ * written. Fields that are absent (because they were added after the version of the record, or because they
+ * were optional) will get their default value, and fields that were written but are never read (because they
+ * were added after the version of the reader) will be skipped.
+ */
+pub struct RecordReader {
+    version: u64,
+    fields: Vec<Option<BitVec>>,
+    next_field: usize,
+}
+
+impl RecordReader {
+    /**
+     * Reads the next record from the given input. All data of the record is read at once, so the input can be
+     * used to read the data after the record directly, even if not all fields of the record will be read. If
+     * the fields of the record have more than max_bits bits of data in total, a LengthError will be
+     * returned instead of allocating memory for the data.
+     */
+    pub fn read(input: &mut dyn BitInput, max_bits: usize) -> Result<RecordReader, BitInputError> {
+        let version = input.read_var_u64()?;
+        // Every field needs at least 1 bit in the presence bitmap
+        let field_count = read_length(input, max_bits)?;
+        input.ensure_extra_capacity(field_count)?;
+        let mut present = Vec::with_capacity(field_count);
+        for _ in 0..field_count {
+            present.push(input.read_bool()?);
+        }
+
+        let mut remaining_bits = max_bits;
+        let mut fields = Vec::with_capacity(field_count);
+        for is_present in present {
+            if is_present {
+                let bit_count = read_length(input, remaining_bits)?;
+                remaining_bits -= bit_count;
+                input.ensure_extra_capacity(bit_count)?;
+                let mut data = BitVec::with_capacity(bit_count);
+                data.add_bits_from_input(input, bit_count)?;
+                fields.push(Some(data));
+            } else {
+                fields.push(None);
+            }
+        }
+        Ok(RecordReader {
+            version,
+            fields,
+            next_field: 0,
+        })
+    }
+
+    /**
+     * Returns the version of the record, which is the version of the RecordWriter that wrote it.
+     */
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /**
+     * Returns the number of fields that the RecordWriter added (including the absent fields).
+     */
+    pub fn field_count(&self) -> usize {
+        self.fields.len()
+    }
+
+    /**
+     * Reads the next field, which was added in since_version, with the read function. If the field is absent,
+     * the default value will be returned instead.
+     */
+    pub fn field<T, F: FnOnce(&mut dyn BitInput) -> Result<T, BitInputError>>(
+        &mut self,
+        since_version: u64,
+        default: T,
+        read: F,
+    ) -> Result<T, BitInputError> {
+        Ok(self.optional_field(since_version, read)?.unwrap_or(default))
+    }
+
+    /**
+     * Reads the next field, which was added in since_version, with the read function. If the field is absent,
+     * None will be returned.
+     */
+    pub fn optional_field<T, F: FnOnce(&mut dyn BitInput) -> Result<T, BitInputError>>(
+        &mut self,
+        since_version: u64,
+        read: F,
+    ) -> Result<Option<T>, BitInputError> {
+        let index = self.next_field;
+        self.next_field += 1;
+        if since_version > self.version {
+            return Ok(None);
+        }
+        match self.fields.get(index) {
+            Some(Some(data)) => Ok(Some(read(&mut BitVecBitInput::new(data))?)),
+            _ => Ok(None),
+        }
+    }
+}
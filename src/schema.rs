@@ -0,0 +1,309 @@
+use crate::input::*;
+use crate::output::*;
+
+/**
+ * Describes the structure of data in a bit stream, so the data can be decoded without compiled types (for
+ * instance by debugging tools). Every node corresponds to the add_ method of BitOutput and the read_ method of
+ * BitInput that store and read it:
+ * - Bool, I8, I16, I32, I64, U8, U16, U32 and U64 use add_bool, add_i8, ..., add_u64
+ * - Sized(bits) uses add_sized_u64 and SignedSized(bits) uses add_sized_i64
+ * - VarU64 uses add_var_u64
+ * - String(max_length) uses add_rust_string, where max_length is the maximum number of UTF-8 bytes
+ * - I32Vec(max_length) uses add_i32_slice and Bytes(max_length) uses add_u8_slice, where max_length is the
+ *   maximum number of elements
+ * - Struct stores its fields one after another, in the given order
+ * - Option stores a bool that tells whether the value is present, followed by the value if it is present
+ * - List(element, max_length) stores the number of elements with add_var_u64, followed by the elements
+ *
+ * Values that are described by a schema can be decoded with decode_with_schema and encoded with
+ * encode_with_schema.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schema {
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    Sized(usize),
+    SignedSized(usize),
+    VarU64,
+    String(usize),
+    I32Vec(usize),
+    Bytes(usize),
+    Struct(Vec<(String, Schema)>),
+    Option(Box<Schema>),
+    List(Box<Schema>, usize),
+}
+
+/**
+ * A value that was decoded with decode_with_schema, or that can be encoded with encode_with_schema. The
+ * Sized and VarU64 schema nodes have U64 values and the SignedSized schema node has I64 values. The values of
+ * the fields of a Struct are paired with the names of the fields.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    String(String),
+    I32Vec(Vec<i32>),
+    Bytes(Vec<u8>),
+    Struct(Vec<(String, Value)>),
+    Option(Option<Box<Value>>),
+    List(Vec<Value>),
+}
+
+impl Schema {
+    /**
+     * Checks whether the given value matches this schema, which means that encode_with_schema can encode it and
+     * that decode_with_schema would decode it again. If it doesn't match, the returned error will tell where
+     * the first mismatch is.
+     */
+    pub fn validate(&self, value: &Value) -> Result<(), SchemaMismatchError> {
+        let matches = match (self, value) {
+            (Schema::Bool, Value::Bool(_))
+            | (Schema::I8, Value::I8(_))
+            | (Schema::I16, Value::I16(_))
+            | (Schema::I32, Value::I32(_))
+            | (Schema::I64, Value::I64(_))
+            | (Schema::U8, Value::U8(_))
+            | (Schema::U16, Value::U16(_))
+            | (Schema::U32, Value::U32(_))
+            | (Schema::U64, Value::U64(_))
+            | (Schema::VarU64, Value::U64(_)) => true,
+            (Schema::Sized(bits), Value::U64(value)) => {
+                *bits == 64 || (*bits < 64 && *value < (1 << *bits))
+            }
+            (Schema::SignedSized(0), Value::I64(value)) => *value == 0,
+            (Schema::SignedSized(bits), Value::I64(value)) => {
+                *bits == 64
+                    || (*bits < 64 && *value >= -(1 << (*bits - 1)) && *value < (1 << (*bits - 1)))
+            }
+            (Schema::String(max_length), Value::String(value)) => value.len() <= *max_length,
+            (Schema::I32Vec(max_length), Value::I32Vec(values)) => values.len() <= *max_length,
+            (Schema::Bytes(max_length), Value::Bytes(values)) => values.len() <= *max_length,
+            (Schema::Struct(fields), Value::Struct(values)) => {
+                if fields.len() != values.len() {
+                    return Err(SchemaMismatchError::new());
+                }
+                for ((name, field), (value_name, value)) in fields.iter().zip(values.iter()) {
+                    if name != value_name {
+                        return Err(SchemaMismatchError::new().inside(&format!(".{}", value_name)));
+                    }
+                    field
+                        .validate(value)
+                        .map_err(|error| error.inside(&format!(".{}", name)))?;
+                }
+                true
+            }
+            (Schema::Option(_), Value::Option(None)) => true,
+            (Schema::Option(element), Value::Option(Some(value))) => {
+                element.validate(value)?;
+                true
+            }
+            (Schema::List(element, max_length), Value::List(values)) => {
+                if values.len() > *max_length {
+                    return Err(SchemaMismatchError::new());
+                }
+                for (index, value) in values.iter().enumerate() {
+                    element
+                        .validate(value)
+                        .map_err(|error| error.inside(&format!("[{}]", index)))?;
+                }
+                true
+            }
+            _ => false,
+        };
+        if matches {
+            Ok(())
+        } else {
+            Err(SchemaMismatchError::new())
+        }
+    }
+}
+
+/**
+ * Reads a value with the given schema from the input. The errors are the errors of the read_ methods that the
+ * schema nodes correspond to. If the number of elements of a List, I32Vec or Bytes is larger than its
+ * max_length, a LengthError will be returned. If the schema has a Sized or SignedSized node with more than 64 bits, an
+ * InvalidCodeError will be returned when that node is reached, since such values can't be read.
+ *
+ * The mirror function of this function is encode_with_schema.
+ */
+pub fn decode_with_schema(
+    input: &mut dyn BitInput,
+    schema: &Schema,
+) -> Result<Value, BitInputError> {
+    Ok(match schema {
+        Schema::Bool => Value::Bool(input.read_bool()?),
+        Schema::I8 => Value::I8(input.read_i8()?),
+        Schema::I16 => Value::I16(input.read_i16()?),
+        Schema::I32 => Value::I32(input.read_i32()?),
+        Schema::I64 => Value::I64(input.read_i64()?),
+        Schema::U8 => Value::U8(input.read_u8()?),
+        Schema::U16 => Value::U16(input.read_u16()?),
+        Schema::U32 => Value::U32(input.read_u32()?),
+        Schema::U64 => Value::U64(input.read_u64()?),
+        Schema::Sized(bits) | Schema::SignedSized(bits) if *bits > 64 => {
+            return Err(BitInputError::InvalidCode(InvalidCodeError));
+        }
+        Schema::Sized(bits) => Value::U64(input.read_sized_u64(*bits)?),
+        Schema::SignedSized(bits) => Value::I64(input.read_sized_i64(*bits)?),
+        Schema::VarU64 => Value::U64(input.read_var_u64()?),
+        Schema::String(max_length) => Value::String(input.read_rust_string(*max_length)?),
+        Schema::I32Vec(max_length) => {
+            // A negative length becomes larger than any allowed maximum when it is converted to a u32
+            let length = check_length(input.read_i32()? as u32, *max_length)?;
+            Value::I32Vec(input.read_i32s(length)?)
+        }
+        Schema::Bytes(max_length) => {
+            let length = check_length(input.read_u32()?, *max_length)?;
+            Value::Bytes(input.read_u8s(length)?)
+        }
+        Schema::Struct(fields) => {
+            let mut values = Vec::with_capacity(fields.len());
+            for (name, field) in fields {
+                values.push((name.clone(), decode_with_schema(input, field)?));
+            }
+            Value::Struct(values)
+        }
+        Schema::Option(element) => {
+            if input.read_bool()? {
+                Value::Option(Some(Box::new(decode_with_schema(input, element)?)))
+            } else {
+                Value::Option(None)
+            }
+        }
+        Schema::List(element, max_length) => {
+            let length = read_length(input, *max_length)?;
+            // Elements can take 0 bits (for instance empty structs), so the capacity can't be checked up front
+            let mut values = Vec::new();
+            for _ in 0..length {
+                values.push(decode_with_schema(input, element)?);
+            }
+            Value::List(values)
+        }
+    })
+}
+
+fn check_length(length: u32, max_length: usize) -> Result<usize, BitInputError> {
+    if length as usize > max_length {
+        return Err(BitInputError::Length(LengthError::new(
+            length as u64,
+            max_length,
+        )));
+    }
+    Ok(length as usize)
+}
+
+/**
+ * Writes the given value with the given schema to the output. The value is validated before anything is
+ * written, so nothing will be written if it doesn't match the schema.
+ *
+ * The mirror function of this function is decode_with_schema.
+ */
+pub fn encode_with_schema(
+    output: &mut dyn BitOutput,
+    schema: &Schema,
+    value: &Value,
+) -> Result<(), SchemaMismatchError> {
+    schema.validate(value)?;
+    write_value(output, schema, value);
+    Ok(())
+}
+
+fn write_value(output: &mut dyn BitOutput, schema: &Schema, value: &Value) {
+    match (schema, value) {
+        (Schema::Bool, Value::Bool(value)) => output.add_bool(*value),
+        (Schema::I8, Value::I8(value)) => output.add_i8(*value),
+        (Schema::I16, Value::I16(value)) => output.add_i16(*value),
+        (Schema::I32, Value::I32(value)) => output.add_i32(*value),
+        (Schema::I64, Value::I64(value)) => output.add_i64(*value),
+        (Schema::U8, Value::U8(value)) => output.add_u8(*value),
+        (Schema::U16, Value::U16(value)) => output.add_u16(*value),
+        (Schema::U32, Value::U32(value)) => output.add_u32(*value),
+        (Schema::U64, Value::U64(value)) => output.add_u64(*value),
+        (Schema::Sized(bits), Value::U64(value)) => output.add_sized_u64(*value, *bits),
+        (Schema::SignedSized(bits), Value::I64(value)) => output.add_sized_i64(*value, *bits),
+        (Schema::VarU64, Value::U64(value)) => output.add_var_u64(*value),
+        (Schema::String(_), Value::String(value)) => output.add_rust_string(value),
+        (Schema::I32Vec(_), Value::I32Vec(values)) => output.add_i32_slice(values),
+        (Schema::Bytes(_), Value::Bytes(values)) => output.add_u8_slice(values),
+        (Schema::Struct(fields), Value::Struct(values)) => {
+            for ((_, field), (_, value)) in fields.iter().zip(values.iter()) {
+                write_value(output, field, value);
+            }
+        }
+        (Schema::Option(element), Value::Option(value)) => {
+            output.add_bool(value.is_some());
+            if let Some(value) = value {
+                write_value(output, element, value);
+            }
+        }
+        (Schema::List(element, _), Value::List(values)) => {
+            output.add_var_u64(values.len() as u64);
+            for value in values {
+                write_value(output, element, value);
+            }
+        }
+        _ => unreachable!("The value should have been validated"),
+    }
+}
+
+/**
+ * This error is returned by encode_with_schema and Schema::validate when a value doesn't match its schema. The
+ * path tells where the mismatch is, for instance ".players[3].name", or it is empty if the value itself doesn't
+ * match.
+ */
+#[derive(Debug, PartialEq)]
+pub struct SchemaMismatchError {
+    path: String,
+}
+
+impl SchemaMismatchError {
+    fn new() -> SchemaMismatchError {
+        SchemaMismatchError {
+            path: String::new(),
+        }
+    }
+
+    fn inside(mut self, segment: &str) -> SchemaMismatchError {
+        self.path.insert_str(0, segment);
+        self
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl std::fmt::Display for SchemaMismatchError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(formatter, "The value doesn't match the schema")
+        } else {
+            write!(
+                formatter,
+                "The value at {} doesn't match the schema",
+                self.path
+            )
+        }
+    }
+}
+
+impl std::error::Error for SchemaMismatchError {
+    fn description(&self) -> &str {
+        "The value doesn't match the schema"
+    }
+}
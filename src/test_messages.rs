@@ -0,0 +1,247 @@
+// Generated by bit_helper from a schema file. Do not edit this file manually.
+
+use crate::input::*;
+use crate::output::*;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    pub id: i32,
+    pub name: Option<String>,
+}
+
+impl Item {
+    pub fn write(&self, output: &mut dyn BitOutput) {
+        output.add_i32(self.id);
+        output.add_str(self.name.as_deref());
+    }
+
+    pub fn read(input: &mut dyn BitInput) -> Result<Item, BitInputError> {
+        let field_id = input.read_i32()?;
+        let field_name = input.read_string(50)?;
+        Ok(Item {
+            id: field_id,
+            name: field_name,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Player {
+    pub alive: bool,
+    pub small: i8,
+    pub medium: i16,
+    pub letter: u16,
+    pub big: i64,
+    pub guild: Option<String>,
+    pub weapon: Option<Item>,
+    pub scores: Vec<Option<i64>>,
+    pub items: Vec<Item>,
+    pub nested: Vec<Vec<i8>>,
+    pub children: Vec<Player>,
+}
+
+impl Player {
+    pub fn write(&self, output: &mut dyn BitOutput) {
+        output.add_bool(self.alive);
+        output.add_i8(self.small);
+        output.add_i16(self.medium);
+        output.add_u16(self.letter);
+        output.add_i64(self.big);
+        output.add_str(self.guild.as_deref());
+        output.add_bool(self.weapon.is_some());
+        if let Some(value_0) = &self.weapon {
+            value_0.write(output);
+        }
+        output.add_i32(self.scores.len() as i32);
+        for element_0 in &self.scores {
+            output.add_bool(element_0.is_some());
+            if let Some(value_1) = element_0 {
+                output.add_i64(*value_1);
+            }
+        }
+        output.add_i32(self.items.len() as i32);
+        for element_0 in &self.items {
+            element_0.write(output);
+        }
+        output.add_i32(self.nested.len() as i32);
+        for element_0 in &self.nested {
+            output.add_i32(element_0.len() as i32);
+            for element_1 in element_0 {
+                output.add_i8(*element_1);
+            }
+        }
+        output.add_i32(self.children.len() as i32);
+        for element_0 in &self.children {
+            element_0.write(output);
+        }
+    }
+
+    pub fn read(input: &mut dyn BitInput) -> Result<Player, BitInputError> {
+        let field_alive = input.read_bool()?;
+        let field_small = input.read_i8()?;
+        let field_medium = input.read_i16()?;
+        let field_letter = input.read_u16()?;
+        let field_big = input.read_i64()?;
+        let field_guild = input.read_string(20)?;
+        let field_weapon = if input.read_bool()? {
+            let value_0 = Item::read(input)?;
+            Some(value_0)
+        } else {
+            None
+        };
+        let length_0 = input.read_i32()? as u32;
+        if length_0 as usize > 10 {
+            return Err(BitInputError::Length(LengthError::new(length_0 as u64, 10)));
+        }
+        let mut field_scores = Vec::with_capacity(length_0 as usize);
+        for _ in 0..length_0 {
+            let element_0 = if input.read_bool()? {
+                let value_1 = input.read_i64()?;
+                Some(value_1)
+            } else {
+                None
+            };
+            field_scores.push(element_0);
+        }
+        let length_0 = input.read_i32()? as u32;
+        if length_0 as usize > 100 {
+            return Err(BitInputError::Length(LengthError::new(length_0 as u64, 100)));
+        }
+        let mut field_items = Vec::with_capacity(length_0 as usize);
+        for _ in 0..length_0 {
+            let element_0 = Item::read(input)?;
+            field_items.push(element_0);
+        }
+        let length_0 = input.read_i32()? as u32;
+        if length_0 as usize > 3 {
+            return Err(BitInputError::Length(LengthError::new(length_0 as u64, 3)));
+        }
+        let mut field_nested = Vec::with_capacity(length_0 as usize);
+        for _ in 0..length_0 {
+            let length_1 = input.read_i32()? as u32;
+            if length_1 as usize > 3 {
+                return Err(BitInputError::Length(LengthError::new(length_1 as u64, 3)));
+            }
+            let mut element_0 = Vec::with_capacity(length_1 as usize);
+            for _ in 0..length_1 {
+                let element_1 = input.read_i8()?;
+                element_0.push(element_1);
+            }
+            field_nested.push(element_0);
+        }
+        let length_0 = input.read_i32()? as u32;
+        if length_0 as usize > 4 {
+            return Err(BitInputError::Length(LengthError::new(length_0 as u64, 4)));
+        }
+        let mut field_children = Vec::with_capacity(length_0 as usize);
+        for _ in 0..length_0 {
+            let element_0 = Player::read(input)?;
+            field_children.push(element_0);
+        }
+        Ok(Player {
+            alive: field_alive,
+            small: field_small,
+            medium: field_medium,
+            letter: field_letter,
+            big: field_big,
+            guild: field_guild,
+            weapon: field_weapon,
+            scores: field_scores,
+            items: field_items,
+            nested: field_nested,
+            children: field_children,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Names {
+    pub items: Vec<i32>,
+    pub items_length: i32,
+    pub items_index: i32,
+    pub items_element: Vec<Option<i8>>,
+    pub weapon: Option<i16>,
+    pub weapon_value: i16,
+    pub length_0: i32,
+    pub value_0: Option<i32>,
+}
+
+impl Names {
+    pub fn write(&self, output: &mut dyn BitOutput) {
+        output.add_i32(self.items.len() as i32);
+        for element_0 in &self.items {
+            output.add_i32(*element_0);
+        }
+        output.add_i32(self.items_length);
+        output.add_i32(self.items_index);
+        output.add_i32(self.items_element.len() as i32);
+        for element_0 in &self.items_element {
+            output.add_bool(element_0.is_some());
+            if let Some(value_1) = element_0 {
+                output.add_i8(*value_1);
+            }
+        }
+        output.add_bool(self.weapon.is_some());
+        if let Some(value_0) = &self.weapon {
+            output.add_i16(*value_0);
+        }
+        output.add_i16(self.weapon_value);
+        output.add_i32(self.length_0);
+        output.add_bool(self.value_0.is_some());
+        if let Some(value_0) = &self.value_0 {
+            output.add_i32(*value_0);
+        }
+    }
+
+    pub fn read(input: &mut dyn BitInput) -> Result<Names, BitInputError> {
+        let length_0 = input.read_i32()? as u32;
+        if length_0 as usize > 5 {
+            return Err(BitInputError::Length(LengthError::new(length_0 as u64, 5)));
+        }
+        let mut field_items = Vec::with_capacity(length_0 as usize);
+        for _ in 0..length_0 {
+            let element_0 = input.read_i32()?;
+            field_items.push(element_0);
+        }
+        let field_items_length = input.read_i32()?;
+        let field_items_index = input.read_i32()?;
+        let length_0 = input.read_i32()? as u32;
+        if length_0 as usize > 5 {
+            return Err(BitInputError::Length(LengthError::new(length_0 as u64, 5)));
+        }
+        let mut field_items_element = Vec::with_capacity(length_0 as usize);
+        for _ in 0..length_0 {
+            let element_0 = if input.read_bool()? {
+                let value_1 = input.read_i8()?;
+                Some(value_1)
+            } else {
+                None
+            };
+            field_items_element.push(element_0);
+        }
+        let field_weapon = if input.read_bool()? {
+            let value_0 = input.read_i16()?;
+            Some(value_0)
+        } else {
+            None
+        };
+        let field_weapon_value = input.read_i16()?;
+        let field_length_0 = input.read_i32()?;
+        let field_value_0 = if input.read_bool()? {
+            let value_0 = input.read_i32()?;
+            Some(value_0)
+        } else {
+            None
+        };
+        Ok(Names {
+            items: field_items,
+            items_length: field_items_length,
+            items_index: field_items_index,
+            items_element: field_items_element,
+            weapon: field_weapon,
+            weapon_value: field_weapon_value,
+            length_0: field_length_0,
+            value_0: field_value_0,
+        })
+    }
+}